@@ -0,0 +1,86 @@
+//! Times day 2's noun/verb search on the interpreter, with and without the
+//! decode cache, and on the compiled backend.
+//!
+//! Uses `inputs/02.txt` when it exists, otherwise a generated program of
+//! the same shape. Run with `cargo bench --bench day02`.
+
+use std::{
+    fs,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use aoc::computer::{compiled::CompiledComputer, machine::Machine, memory::Memory, Computer, Word};
+
+const ROUNDS: usize = 5;
+
+fn program() -> Memory {
+    match fs::read_to_string("inputs/02.txt") {
+        Ok(input) => input.parse().unwrap(),
+        Err(_) => generated(),
+    }
+}
+
+/// Like the real inputs, each instruction writes into its own last cell and
+/// the next one reads it back, with a block of constants after the `99`.
+fn generated() -> Memory {
+    const STEPS: usize = 40;
+    const CONSTANTS: usize = 10;
+    let constants = 4 + STEPS * 4 + 1;
+
+    let mut program: Vec<Word> = vec![1, 0, 0, 3];
+    for step in 0..STEPS {
+        let opcode = if step % 5 == 0 { 2 } else { 1 };
+        let previous = program.len() - 1;
+        let constant = constants + step % CONSTANTS;
+        let own = program.len() + 3;
+        program.extend([opcode, previous as Word, constant as Word, own as Word]);
+    }
+    program.push(99);
+    program.extend((1..=CONSTANTS).map(|value| value as Word));
+    Memory::new(program)
+}
+
+fn search<M: Machine + Clone>(template: &M) -> Duration {
+    let start = Instant::now();
+    for noun in 0..=99 {
+        for verb in 0..=99 {
+            let mut computer = template.clone();
+            computer.set_memory(1, noun);
+            computer.set_memory(2, verb);
+            computer.run().unwrap();
+            black_box(computer.memory().first());
+        }
+    }
+    start.elapsed()
+}
+
+fn best<M: Machine + Clone>(template: &M) -> Duration {
+    (0..ROUNDS).map(|_| search(template)).min().unwrap()
+}
+
+fn main() {
+    let memory = program();
+
+    let mut uncached = Computer::new(memory.clone());
+    uncached.set_decode_cache(false);
+    let mut cached = Computer::new(memory.clone());
+    cached.predecode();
+    let compiled = CompiledComputer::new(memory);
+
+    let uncached = best(&uncached);
+    let cached = best(&cached);
+    let compiled = best(&compiled);
+    println!("10000 runs, best of {}", ROUNDS);
+    println!("uncached: {:>10.2?}", uncached);
+    println!(
+        "cached:   {:>10.2?} ({:.1}x)",
+        cached,
+        uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+    println!(
+        "compiled: {:>10.2?} ({:.1}x)",
+        compiled,
+        uncached.as_secs_f64() / compiled.as_secs_f64()
+    );
+}
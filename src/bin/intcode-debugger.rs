@@ -0,0 +1,394 @@
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use aoc::computer::{
+    instruction::Instruction,
+    instruction_set::InstructionSet,
+    memory::Memory,
+    watch::{Reason, Watch},
+    Computer, RunState, Word,
+};
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Interactive debugger for Intcode programs", long_about = None)]
+struct Args {
+    /// Intcode program to load on startup
+    program: Option<PathBuf>,
+}
+
+const HELP: &str = "\
+load <file>            load a program, resetting the machine
+break <addr>           set a breakpoint
+delete <addr>          remove a breakpoint
+breakpoints            list breakpoints
+watch <addr>           stop when addr is written
+unwatch <addr>         remove a watchpoint
+step [n]               execute n instructions (default 1)
+continue               run until a breakpoint, input is needed, or the program halts
+regs                   show pointer and relative base
+where                  show the instruction at the pointer
+mem <addr> [len]       dump memory
+poke <addr> <value>..  write values starting at addr
+input <value>..        queue input values
+output                 show pending output
+quit                   exit";
+
+/// Addresses past this are rejected, so that a typo can't make `poke`
+/// allocate memory all the way up to it.
+const MAX_ADDRESS: usize = 1 << 24;
+/// Most cells `mem` shows at once.
+const MAX_DUMP: usize = 4096;
+
+struct Debugger {
+    /// Custom opcodes for every program loaded.
+    instruction_set: Option<InstructionSet>,
+    computer: Option<Computer>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+}
+
+enum Reply {
+    Text(String),
+    Quit,
+}
+
+impl Debugger {
+    pub fn new(instruction_set: Option<InstructionSet>) -> Self {
+        Self {
+            instruction_set,
+            computer: None,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+        }
+    }
+
+    pub fn load(&mut self, program: &str) -> Result<(), String> {
+        let memory: Memory = program
+            .parse()
+            .map_err(|err| format!("invalid program: {}", err))?;
+        let mut computer = Computer::new(memory);
+        if let Some(set) = &self.instruction_set {
+            computer.set_instruction_set(set.clone());
+        }
+        self.computer = Some(computer);
+        Ok(())
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<Reply, String> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(Reply::Text(String::new()));
+        };
+        let args: Vec<&str> = words.collect();
+
+        let text = match command {
+            "help" | "h" => HELP.to_string(),
+            "quit" | "q" => return Ok(Reply::Quit),
+            "load" | "l" => {
+                let path = args.first().ok_or("usage: load <file>")?;
+                let program = fs::read_to_string(path).map_err(|err| err.to_string())?;
+                self.load(&program)?;
+                format!("loaded {}", path)
+            }
+            "break" | "b" => {
+                let address = parse_address(args.first())?;
+                self.breakpoints.insert(address);
+                format!("breakpoint at {}", address)
+            }
+            "delete" | "d" => {
+                let address = parse_address(args.first())?;
+                if self.breakpoints.remove(&address) {
+                    format!("removed breakpoint at {}", address)
+                } else {
+                    format!("no breakpoint at {}", address)
+                }
+            }
+            "watch" => {
+                let address = parse_address(args.first())?;
+                self.watchpoints.insert(address);
+                format!("watchpoint at {}", address)
+            }
+            "unwatch" => {
+                let address = parse_address(args.first())?;
+                if self.watchpoints.remove(&address) {
+                    format!("removed watchpoint at {}", address)
+                } else {
+                    format!("no watchpoint at {}", address)
+                }
+            }
+            "breakpoints" => self
+                .breakpoints
+                .iter()
+                .map(|address| address.to_string())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "step" | "s" => {
+                let count = match args.first() {
+                    Some(arg) => arg.parse::<usize>().map_err(|err| err.to_string())?,
+                    None => 1,
+                };
+                self.step(count)?
+            }
+            "continue" | "c" => self.resume()?,
+            "regs" | "r" => {
+                let computer = self.computer()?;
+                format!(
+                    "pointer={} relative_base={}",
+                    computer.pointer(),
+                    computer.relative_base()
+                )
+            }
+            "where" | "w" => self.current_instruction()?,
+            "mem" | "x" => {
+                let start = parse_address(args.first())?;
+                let len = match args.get(1) {
+                    Some(arg) => arg.parse::<usize>().map_err(|err| err.to_string())?,
+                    None => 8,
+                };
+                self.dump(start, len)?
+            }
+            "poke" | "p" => {
+                let start = parse_address(args.first())?;
+                let values = parse_values(&args[1..])?;
+                if start + values.len() > MAX_ADDRESS + 1 {
+                    return Err(format!("values run past address {}", MAX_ADDRESS));
+                }
+                let computer = self.computer_mut()?;
+                for (offset, value) in values.iter().enumerate() {
+                    computer.set_memory(start + offset, *value);
+                }
+                format!("wrote {} value(s) at {}", values.len(), start)
+            }
+            "input" | "i" => {
+                let values = parse_values(&args)?;
+                let computer = self.computer_mut()?;
+                for value in values.iter() {
+                    computer.push_input(*value);
+                }
+                format!("queued {} value(s)", values.len())
+            }
+            "output" | "o" => {
+                let output = self.computer()?.get_output();
+                output
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }
+            _ => return Err(format!("unknown command `{}`, try `help`", command)),
+        };
+
+        Ok(Reply::Text(text))
+    }
+
+    fn computer(&self) -> Result<&Computer, String> {
+        self.computer
+            .as_ref()
+            .ok_or_else(|| "no program loaded".to_string())
+    }
+
+    fn computer_mut(&mut self) -> Result<&mut Computer, String> {
+        self.computer
+            .as_mut()
+            .ok_or_else(|| "no program loaded".to_string())
+    }
+
+    fn step(&mut self, count: usize) -> Result<String, String> {
+        let computer = self.computer_mut()?;
+        for _ in 0..count {
+            if computer.step().map_err(|err| err.to_string())?.is_none() {
+                return Ok(Self::stopped(computer));
+            }
+        }
+        self.current_instruction()
+    }
+
+    fn resume(&mut self) -> Result<String, String> {
+        let computer = self.computer.as_mut().ok_or("no program loaded")?;
+        computer.clear_watches();
+        for address in &self.breakpoints {
+            computer.add_watch(Watch::Pointer(*address));
+        }
+        for address in &self.watchpoints {
+            computer.add_watch(Watch::Write(*address));
+        }
+        let state = computer.run().map_err(|err| err.to_string())?;
+        computer.clear_watches();
+
+        let stop = match state {
+            RunState::Watch(hit) => match hit.reason {
+                Reason::Write { pointer, write } => format!(
+                    "watchpoint: {} wrote [{}] {} -> {}",
+                    pointer, write.address, write.old, write.new
+                ),
+                _ => "breakpoint".to_string(),
+            },
+            _ => return Ok(Self::stopped(computer)),
+        };
+        let instruction = self.current_instruction()?;
+        Ok(format!("{}\n{}", stop, instruction))
+    }
+
+    fn stopped(computer: &Computer) -> String {
+        if computer.is_halted() {
+            "halted".to_string()
+        } else {
+            format!("waiting for input at {}", computer.pointer())
+        }
+    }
+
+    fn current_instruction(&self) -> Result<String, String> {
+        let computer = self.computer()?;
+        let pointer = computer.pointer();
+        let set = computer.instruction_set();
+        match Instruction::decode_with(computer.memory(), pointer, set) {
+            Ok(instruction) => Ok(format!("{:>6}  {}", pointer, instruction)),
+            Err(err) => Ok(format!("{:>6}  <{}>", pointer, err)),
+        }
+    }
+
+    fn dump(&self, start: usize, len: usize) -> Result<String, String> {
+        let computer = self.computer()?;
+        if len > MAX_DUMP {
+            return Err(format!("can show at most {} cells", MAX_DUMP));
+        }
+        let end = start
+            .checked_add(len)
+            .ok_or_else(|| format!("invalid length {}", len))?;
+        let lines = (start..end)
+            .step_by(8)
+            .map(|row| {
+                let values = (row..end.min(row + 8))
+                    .map(|address| computer.memory().get(address).copied().unwrap_or(0))
+                    .map(|value| format!("{:>8}", value))
+                    .collect::<String>();
+                format!("{:>6}:{}", row, values)
+            })
+            .collect::<Vec<_>>();
+        Ok(lines.join("\n"))
+    }
+}
+
+fn parse_address(arg: Option<&&str>) -> Result<usize, String> {
+    let arg = arg.ok_or("missing address")?;
+    match arg.parse::<usize>() {
+        Ok(address) if address <= MAX_ADDRESS => Ok(address),
+        Ok(_) => Err(format!("address `{}` is past {}", arg, MAX_ADDRESS)),
+        Err(_) => Err(format!("invalid address `{}`", arg)),
+    }
+}
+
+fn parse_values(args: &[&str]) -> Result<Vec<Word>, String> {
+    args.iter()
+        .flat_map(|arg| arg.split(','))
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| {
+            arg.parse::<Word>()
+                .map_err(|_| format!("invalid value `{}`", arg))
+        })
+        .collect()
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    // custom ops are closures, so there is no way to load them from the
+    // command line
+    let mut debugger = Debugger::new(None);
+    if let Some(path) = args.program {
+        debugger.load(&fs::read_to_string(path)?)?;
+    }
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    loop {
+        write!(stdout, "(icdb) ")?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        match debugger.execute(&line) {
+            Ok(Reply::Text(text)) if text.is_empty() => {}
+            Ok(Reply::Text(text)) => writeln!(stdout, "{}", text)?,
+            Ok(Reply::Quit) => return Ok(()),
+            Err(err) => writeln!(stdout, "error: {}", err)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aoc::computer::instruction_set::{CustomOp, InstructionSet};
+
+    use super::{Debugger, Reply};
+
+    fn run(debugger: &mut Debugger, line: &str) -> String {
+        match debugger.execute(line) {
+            Ok(Reply::Text(text)) => text,
+            Ok(Reply::Quit) => "quit".to_string(),
+            Err(err) => format!("error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_debugger() {
+        let mut debugger = Debugger::new(None);
+        assert_eq!(run(&mut debugger, "regs"), "error: no program loaded");
+
+        debugger.load("3,11,1001,11,1,11,4,11,99,0,0,0\n").unwrap();
+        assert_eq!(run(&mut debugger, "break 6"), "breakpoint at 6");
+        assert_eq!(run(&mut debugger, "c"), "waiting for input at 0");
+        assert_eq!(run(&mut debugger, "input 41"), "queued 1 value(s)");
+        assert_eq!(
+            run(&mut debugger, "step"),
+            "     2  ADD [pos 11], #1, [pos 11]"
+        );
+        assert_eq!(
+            run(&mut debugger, "continue"),
+            "breakpoint\n     6  OUT [pos 11]"
+        );
+        assert_eq!(run(&mut debugger, "regs"), "pointer=6 relative_base=0");
+        assert_eq!(run(&mut debugger, "poke 11 99"), "wrote 1 value(s) at 11");
+        assert_eq!(
+            run(&mut debugger, "mem 8 4"),
+            "     8:      99       0       0      99"
+        );
+        assert_eq!(
+            run(&mut debugger, "poke 99999999999 1"),
+            "error: address `99999999999` is past 16777216"
+        );
+        assert_eq!(
+            run(&mut debugger, "mem 8 99999999"),
+            "error: can show at most 4096 cells"
+        );
+        assert_eq!(run(&mut debugger, "c"), "halted");
+        assert_eq!(run(&mut debugger, "output"), "99");
+
+        debugger.load("3,11,1001,11,1,11,4,11,99,0,0,0\n").unwrap();
+        assert_eq!(run(&mut debugger, "watch 11"), "watchpoint at 11");
+        assert_eq!(run(&mut debugger, "input 1"), "queued 1 value(s)");
+        assert_eq!(
+            run(&mut debugger, "c"),
+            "watchpoint: 0 wrote [11] 0 -> 1\n     2  ADD [pos 11], #1, [pos 11]"
+        );
+        assert_eq!(run(&mut debugger, "unwatch 11"), "removed watchpoint at 11");
+        assert_eq!(run(&mut debugger, "c"), "breakpoint\n     6  OUT [pos 11]");
+        assert_eq!(run(&mut debugger, "quit"), "quit");
+
+        let mut set = InstructionSet::new();
+        set.register(CustomOp::new(20, "NOP", 1, None, |_| None))
+            .unwrap();
+        let mut debugger = Debugger::new(Some(set));
+        debugger.load("20,0,99").unwrap();
+        assert_eq!(run(&mut debugger, "where"), "     0  NOP [pos 0]");
+        assert_eq!(run(&mut debugger, "step"), "     2  HLT");
+    }
+}
@@ -0,0 +1,9 @@
+use std::{error::Error, fs, path::Path};
+
+use aoc::computer::assembler::assemble;
+
+pub(crate) fn run(source: &Path) -> Result<(), Box<dyn Error>> {
+    let memory = assemble(&fs::read_to_string(source)?)?;
+    println!("{}", memory);
+    Ok(())
+}
@@ -0,0 +1,9 @@
+use std::{error::Error, fs, path::Path};
+
+use aoc::computer::{cfg::control_flow, memory::Memory};
+
+pub(crate) fn run(program: &Path) -> Result<(), Box<dyn Error>> {
+    let memory: Memory = fs::read_to_string(program)?.parse()?;
+    print!("{}", control_flow(&memory).to_dot());
+    Ok(())
+}
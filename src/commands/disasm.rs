@@ -0,0 +1,9 @@
+use std::{error::Error, fs, path::Path};
+
+use aoc::computer::{disassembler::disassemble, memory::Memory};
+
+pub(crate) fn run(program: &Path) -> Result<(), Box<dyn Error>> {
+    let memory: Memory = fs::read_to_string(program)?.parse()?;
+    print!("{}", disassemble(&memory));
+    Ok(())
+}
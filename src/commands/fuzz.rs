@@ -0,0 +1,23 @@
+use std::{error::Error, fs, path::Path};
+
+use aoc::computer::{fuzz::fuzz, memory::Memory};
+use itertools::Itertools;
+
+/// Writes the minimized program to `out`, and its input on the next line in
+/// the form `run` reads it, so the mismatch can be replayed with
+/// `tail -n 1 OUT | aoc run <(head -n 1 OUT)`.
+pub(crate) fn run(seed: u64, runs: usize, steps: usize, out: &Path) -> Result<(), Box<dyn Error>> {
+    let Some(mismatch) = fuzz(seed, runs, steps) else {
+        println!("no mismatches in {} programs", runs);
+        return Ok(());
+    };
+    let program = Memory::new(mismatch.case.program.clone());
+    let input = mismatch.case.input.iter().join(",");
+    fs::write(out, format!("{}\n{}\n", program, input))?;
+    println!("{}", mismatch);
+    Err(format!(
+        "found a mismatch, program and input written to {}",
+        out.display()
+    )
+    .into())
+}
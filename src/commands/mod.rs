@@ -0,0 +1,6 @@
+pub(crate) mod asm;
+pub(crate) mod cfg;
+pub(crate) mod disasm;
+#[cfg(feature = "fuzz")]
+pub(crate) mod fuzz;
+pub(crate) mod run;
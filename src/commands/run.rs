@@ -0,0 +1,147 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use aoc::computer::{ascii, memory::Memory, Computer, RunState, Word};
+
+pub(crate) struct Options {
+    pub ascii: bool,
+    pub patches: Vec<(usize, Word)>,
+    pub yield_on_output: bool,
+}
+
+/// Parses a memory patch given as `ADDRESS=VALUE`.
+pub(crate) fn parse_patch(patch: &str) -> Result<(usize, Word), String> {
+    let (address, value) = patch
+        .split_once('=')
+        .ok_or_else(|| format!("expected ADDRESS=VALUE, got `{}`", patch))?;
+    let address = address
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&address| address < usize::MAX)
+        .ok_or_else(|| format!("invalid address `{}`", address))?;
+    let value = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid value `{}`", value))?;
+    Ok((address, value))
+}
+
+pub(crate) fn run(program: &Path, options: &Options) -> Result<(), Box<dyn Error>> {
+    let memory: Memory = fs::read_to_string(program)?.parse()?;
+    execute(memory, options, io::stdin().lock(), io::stdout().lock())
+}
+
+/// Reads a line of input each time the program needs some. Output is
+/// written whenever the program stops for input or halts, or as soon as it
+/// is produced when yielding on output.
+fn execute(
+    memory: Memory,
+    options: &Options,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let mut computer = Computer::new(memory);
+    computer.set_yield_on_output(options.yield_on_output);
+    for &(address, value) in &options.patches {
+        computer.check_write(address)?;
+        computer.set_memory(address, value);
+    }
+
+    loop {
+        let state = computer.run()?;
+        if let RunState::Output(value) = state {
+            write_value(&mut output, value, options.ascii)?;
+        }
+        while let Some(value) = computer.next_output() {
+            write_value(&mut output, value, options.ascii)?;
+        }
+        output.flush()?;
+
+        match state {
+            RunState::Halted => return Ok(()),
+            RunState::Output(_) | RunState::Watch(_) => {}
+            RunState::NeedsInput => {
+                let mut line = String::new();
+                if input.read_line(&mut line)? == 0 {
+                    return Err("program needs input but stdin is closed".into());
+                }
+                let line = line.trim_end_matches(['\n', '\r']);
+                if options.ascii {
+                    computer.push_line(line)?;
+                } else {
+                    for token in line.split(|c: char| c == ',' || c.is_whitespace()) {
+                        if !token.is_empty() {
+                            let value = token
+                                .parse()
+                                .map_err(|_| format!("invalid input `{}`", token))?;
+                            computer.push_input(value);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// In ASCII mode, values outside `0..=127` are written as numbers on their
+/// own line.
+fn write_value(output: &mut impl Write, value: Word, ascii: bool) -> io::Result<()> {
+    match ascii::to_char(value).filter(|_| ascii) {
+        Some(c) => write!(output, "{}", c),
+        None => writeln!(output, "{}", value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{execute, parse_patch, Options};
+
+    #[test]
+    fn test_execute() {
+        // reads two numbers and prints their sum, or their product once the
+        // add is patched into a multiply
+        let program = "3,11,3,12,1,11,12,13,4,13,99,0,0,0";
+        let mut options = Options {
+            ascii: false,
+            patches: vec![],
+            yield_on_output: false,
+        };
+        let mut output = vec![];
+        let input = "2 3\n".as_bytes();
+        execute(program.parse().unwrap(), &options, input, &mut output).unwrap();
+        assert_eq!(output, b"5\n");
+
+        options.patches.push(parse_patch("4=2").unwrap());
+        output.clear();
+        let input = "2\n3\n".as_bytes();
+        execute(program.parse().unwrap(), &options, input, &mut output).unwrap();
+        assert_eq!(output, b"6\n");
+
+        let result = execute(program.parse().unwrap(), &options, "2".as_bytes(), vec![]);
+        assert!(result.is_err());
+        options.patches = vec![parse_patch("1000000000000000=1").unwrap()];
+        let result = execute(program.parse().unwrap(), &options, input, vec![]);
+        assert!(result.is_err());
+        assert!(parse_patch("4").is_err());
+    }
+
+    #[test]
+    fn test_ascii() {
+        let options = Options {
+            ascii: true,
+            patches: vec![],
+            yield_on_output: true,
+        };
+        let mut output = vec![];
+        let program = "104,72,104,105,104,10,104,1000,99";
+        execute(program.parse().unwrap(), &options, io::empty(), &mut output).unwrap();
+        assert_eq!(output, b"Hi\n1000\n");
+    }
+}
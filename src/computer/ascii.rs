@@ -0,0 +1,99 @@
+use std::fmt;
+
+use super::Word;
+
+/// Output split into text and the values that aren't ASCII, such as the
+/// final answer many text-based programs print after their log.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Ascii {
+    pub text: String,
+    /// Values outside `0..=127`, in order.
+    pub values: Vec<Word>,
+}
+
+impl Ascii {
+    pub fn decode(output: impl IntoIterator<Item = Word>) -> Self {
+        let mut ascii = Self::default();
+        for value in output {
+            match to_char(value) {
+                Some(c) => ascii.text.push(c),
+                None => ascii.values.push(value),
+            }
+        }
+        ascii
+    }
+}
+
+/// The character for `value`, if it is in `0..=127`.
+pub fn to_char(value: Word) -> Option<char> {
+    u8::try_from(value)
+        .ok()
+        .filter(u8::is_ascii)
+        .map(char::from)
+}
+
+/// A character that can't be sent to an ASCII program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NotAscii(pub char);
+
+impl fmt::Display for NotAscii {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` is not ASCII", self.0)
+    }
+}
+
+impl std::error::Error for NotAscii {}
+
+/// `line` as ASCII codes followed by a newline.
+pub fn encode_line(line: &str) -> Result<Vec<Word>, NotAscii> {
+    line.chars()
+        .chain(['\n'])
+        .map(|c| {
+            if c.is_ascii() {
+                Ok(Word::from(c as u8))
+            } else {
+                Err(NotAscii(c))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_line, Ascii, NotAscii};
+    use crate::computer::{assembler::assemble, Computer, RunState};
+
+    #[test]
+    fn test_ascii() {
+        assert_eq!(encode_line("Hi"), Ok(vec![72, 105, 10]));
+        assert_eq!(encode_line("café"), Err(NotAscii('é')));
+
+        // echoes its input until a newline, then prints 1000
+        let echo = assemble(
+            "
+            loop:   IN [char]
+                    OUT [char]
+                    EQ [char], #10, [done]
+                    JF [done], loop
+                    OUT #1000
+                    HLT
+            char:   .data 0
+            done:   .data 0
+            ",
+        )
+        .unwrap();
+        let mut computer = Computer::new(echo);
+        assert!(computer.push_line("NOT A ∅").is_err());
+        computer.push_line("NOT A J").unwrap();
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(
+            computer.take_ascii(),
+            Ascii {
+                text: "NOT A J\n".to_string(),
+                values: vec![1000]
+            }
+        );
+        assert!(computer.get_output().is_empty());
+        assert_eq!(Ascii::decode([-1, 65, 128]).values, vec![-1, 128]);
+    }
+}
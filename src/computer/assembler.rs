@@ -0,0 +1,515 @@
+use std::{collections::HashMap, fmt, iter};
+
+use super::{
+    instruction::{Instruction, Opcode},
+    instruction_set::InstructionSet,
+    memory::Memory,
+    Word,
+};
+
+/// Compiles Intcode assembly into a memory image.
+///
+/// ```text
+/// ; comments run to the end of the line
+/// start:  IN [counter]            ; position mode, by label or `[pos 12]`
+///         ADD [counter], #-1, [rel +2]
+///         JT [counter], start     ; a bare label is its address as an immediate
+///         HLT
+/// counter: .data 0
+/// ```
+///
+/// A leading address column, as printed by the disassembler, is ignored.
+pub fn assemble(source: &str) -> Result<Memory, AssembleError> {
+    assemble_with(source, None)
+}
+
+/// Like `assemble`, also accepting the mnemonics of the custom opcodes in
+/// `set`.
+pub fn assemble_with(source: &str, set: Option<&InstructionSet>) -> Result<Memory, AssembleError> {
+    let ops = ops(set);
+    let mut statements = vec![];
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut address = 0;
+
+    for (index, text) in source.lines().enumerate() {
+        let mut line = Line::new(text, index + 1);
+        let statement = line.parse_statement(&ops)?;
+
+        if let Some((label, column)) = &statement.label {
+            if labels.insert(label.clone(), address).is_some() {
+                return Err(line.error_at(*column, format!("label `{}` already defined", label)));
+            }
+        }
+        if let Some(body) = statement.body {
+            address += body.size();
+            statements.push(body);
+        }
+    }
+
+    let mut data = Vec::with_capacity(address);
+    for statement in statements {
+        match statement {
+            Body::Instruction { code, params } => {
+                let mut opcode = code;
+                let mut place = 100;
+                for param in params.iter() {
+                    opcode += param.mode * place;
+                    place *= 10;
+                }
+                data.push(opcode);
+                for param in params {
+                    data.push(param.expr.resolve(&labels)?);
+                }
+            }
+            Body::Data(exprs) => {
+                for expr in exprs {
+                    data.push(expr.resolve(&labels)?);
+                }
+            }
+        }
+    }
+
+    Ok(Memory::new(data))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssembleError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+/// Codes of the standard instructions.
+const STANDARD_CODES: [usize; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+
+/// An instruction the assembler knows by its mnemonic.
+struct Op {
+    mnemonic: &'static str,
+    code: Word,
+    arity: usize,
+    /// Index of the parameter written to, if any.
+    write: Option<usize>,
+}
+
+/// The standard instructions, as `Instruction` names them, then the custom
+/// ones in `set`.
+fn ops(set: Option<&InstructionSet>) -> Vec<Op> {
+    let standard = STANDARD_CODES.into_iter().map(|code| {
+        let instruction = Instruction::from_opcode(Opcode::from(code), iter::repeat(&0))
+            .expect("standard opcodes decode");
+        Op {
+            mnemonic: instruction.mnemonic(),
+            code: code as Word,
+            arity: instruction.size() - 1,
+            write: instruction.write(),
+        }
+    });
+    let custom = set
+        .into_iter()
+        .flat_map(|set| (1..100).filter_map(|code| set.get(code)))
+        .map(|op| Op {
+            mnemonic: op.mnemonic,
+            code: op.code as Word,
+            arity: op.arity,
+            write: op.write,
+        });
+    standard.chain(custom).collect()
+}
+
+struct Statement {
+    label: Option<(String, usize)>,
+    body: Option<Body>,
+}
+
+enum Body {
+    Instruction { code: Word, params: Vec<ParamExpr> },
+    Data(Vec<Expr>),
+}
+
+impl Body {
+    fn size(&self) -> usize {
+        match self {
+            Self::Instruction { params, .. } => params.len() + 1,
+            Self::Data(exprs) => exprs.len(),
+        }
+    }
+}
+
+struct ParamExpr {
+    mode: Word,
+    expr: Expr,
+}
+
+struct Expr {
+    label: Option<String>,
+    offset: Word,
+    line: usize,
+    column: usize,
+}
+
+impl Expr {
+    fn resolve(&self, labels: &HashMap<String, usize>) -> Result<Word, AssembleError> {
+        let Some(label) = &self.label else {
+            return Ok(self.offset);
+        };
+        match labels.get(label) {
+            Some(address) => Ok(*address as Word + self.offset),
+            None => Err(AssembleError {
+                line: self.line,
+                column: self.column,
+                message: format!("undefined label `{}`", label),
+            }),
+        }
+    }
+}
+
+struct Line {
+    chars: Vec<char>,
+    pos: usize,
+    number: usize,
+}
+
+impl Line {
+    fn new(text: &str, number: usize) -> Self {
+        let code = text.split(';').next().unwrap_or("");
+        Self {
+            chars: code.chars().collect(),
+            pos: 0,
+            number,
+        }
+    }
+
+    fn parse_statement(&mut self, ops: &[Op]) -> Result<Statement, AssembleError> {
+        self.skip_whitespace();
+        if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            // address column from a disassembler listing
+            self.take_while(|c| c.is_ascii_digit());
+            self.skip_whitespace();
+        }
+
+        let mut label = None;
+        let mut word = self.peek_word();
+        if let Some((name, column)) = &word {
+            let after = self.pos + name.chars().count();
+            if self.chars.get(after) == Some(&':') {
+                label = Some((name.clone(), *column));
+                self.pos = after + 1;
+                self.skip_whitespace();
+                word = self.peek_word();
+            }
+        }
+
+        let body = match word {
+            None if self.at_end() => None,
+            None => return Err(self.error("expected a mnemonic or directive")),
+            Some((word, column)) => {
+                self.pos += word.chars().count();
+                Some(self.parse_body(ops, &word, column)?)
+            }
+        };
+
+        self.skip_whitespace();
+        if !self.at_end() {
+            return Err(self.error("unexpected trailing input"));
+        }
+
+        Ok(Statement { label, body })
+    }
+
+    fn parse_body(&mut self, ops: &[Op], word: &str, column: usize) -> Result<Body, AssembleError> {
+        if word == ".data" {
+            let mut exprs = vec![self.parse_expr()?];
+            while self.eat(',') {
+                exprs.push(self.parse_expr()?);
+            }
+            return Ok(Body::Data(exprs));
+        }
+
+        let upper = word.to_ascii_uppercase();
+        let Some(&Op {
+            code, arity, write, ..
+        }) = ops.iter().find(|op| op.mnemonic.eq_ignore_ascii_case(word))
+        else {
+            return Err(self.error_at(column, format!("unknown mnemonic `{}`", word)));
+        };
+
+        let mut params = Vec::with_capacity(arity);
+        for index in 0..arity {
+            if index > 0 && !self.eat(',') {
+                return Err(self.error(format!("`{}` takes {} parameters", upper, arity)));
+            }
+            self.skip_whitespace();
+            let start = self.column();
+            let param = self.parse_param()?;
+            if write == Some(index) && param.mode == 1 {
+                return Err(self.error_at(start, "cannot write to an immediate parameter"));
+            }
+            params.push(param);
+        }
+        self.skip_whitespace();
+        if self.peek() == Some(',') {
+            return Err(self.error(format!("`{}` takes {} parameters", upper, arity)));
+        }
+
+        Ok(Body::Instruction { code, params })
+    }
+
+    fn parse_param(&mut self) -> Result<ParamExpr, AssembleError> {
+        self.skip_whitespace();
+        if self.eat('#') {
+            return Ok(ParamExpr {
+                mode: 1,
+                expr: self.parse_expr()?,
+            });
+        }
+        if !self.eat('[') {
+            if self.peek_word().is_some() {
+                return Ok(ParamExpr {
+                    mode: 1,
+                    expr: self.parse_expr()?,
+                });
+            }
+            return Err(self.error("expected `#`, `[` or a label"));
+        }
+
+        self.skip_whitespace();
+        let mut mode = 0;
+        if let Some((word, _)) = self.peek_word() {
+            if word == "rel" || word == "pos" {
+                self.pos += 3;
+                mode = if word == "rel" { 2 } else { 0 };
+            }
+        }
+
+        let expr = if mode == 2 {
+            self.skip_whitespace();
+            let column = self.column();
+            match self.peek() {
+                Some(']') => Expr {
+                    label: None,
+                    offset: 0,
+                    line: self.number,
+                    column,
+                },
+                Some('+') | Some('-') => self.parse_expr()?,
+                _ => return Err(self.error("expected `+` or `-` after `rel`")),
+            }
+        } else {
+            self.parse_expr()?
+        };
+
+        self.skip_whitespace();
+        if !self.eat(']') {
+            return Err(self.error("expected `]`"));
+        }
+        Ok(ParamExpr { mode, expr })
+    }
+
+    /// A number, a label, or a label plus or minus a number.
+    fn parse_expr(&mut self) -> Result<Expr, AssembleError> {
+        self.skip_whitespace();
+        let column = self.column();
+        let mut expr = Expr {
+            label: None,
+            offset: 0,
+            line: self.number,
+            column,
+        };
+
+        if let Some((word, _)) = self.peek_word() {
+            self.pos += word.chars().count();
+            expr.label = Some(word);
+            self.skip_whitespace();
+            if !matches!(self.peek(), Some('+') | Some('-')) {
+                return Ok(expr);
+            }
+        }
+
+        let start = self.pos;
+        if matches!(self.peek(), Some('+') | Some('-')) {
+            self.pos += 1;
+            self.skip_whitespace();
+        }
+        let sign: String = self.chars[start..self.pos]
+            .iter()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        if digits.is_empty() {
+            return Err(self.error("expected a number or label"));
+        }
+        expr.offset = format!("{}{}", sign, digits)
+            .parse()
+            .map_err(|_| self.error_at(column, "number out of range"))?;
+        Ok(expr)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_word(&self) -> Option<(String, usize)> {
+        let first = self.peek()?;
+        if !(first.is_ascii_alphabetic() || first == '_' || first == '.') {
+            return None;
+        }
+        let word: String = self.chars[self.pos..]
+            .iter()
+            .take_while(|c| c.is_ascii_alphanumeric() || **c == '_' || **c == '.')
+            .collect();
+        Some((word, self.column()))
+    }
+
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&pred) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn column(&self) -> usize {
+        self.pos + 1
+    }
+
+    fn error(&self, message: impl Into<String>) -> AssembleError {
+        self.error_at(self.column(), message)
+    }
+
+    fn error_at(&self, column: usize, message: impl Into<String>) -> AssembleError {
+        AssembleError {
+            line: self.number,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assemble, assemble_with, AssembleError};
+    use crate::computer::{
+        disassembler::{disassemble, disassemble_with},
+        instruction_set::{CustomOp, InstructionSet},
+        Computer,
+    };
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; count down from the input value
+            start:  IN [counter]
+            loop:   OUT [counter]
+                    ADD [counter], #-1, [pos 12]
+                    JT [counter], loop
+                    HLT
+            counter: .data 0
+                    .data start, counter+1
+        ";
+        let memory = assemble(source).unwrap();
+        assert_eq!(
+            memory.to_vec(),
+            vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0, 0, 13]
+        );
+
+        let memory = assemble("ARB #3\nOUT [rel -1]\nIN [rel]\nHLT").unwrap();
+        assert_eq!(memory.to_vec(), vec![109, 3, 204, -1, 203, 0, 99]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99".to_string();
+        let computer = Computer::new(program.into());
+        let listing = disassemble(computer.memory());
+        let memory = assemble(&listing).unwrap();
+        assert_eq!(memory.to_vec(), computer.memory().to_vec());
+    }
+
+    #[test]
+    fn test_custom_ops() {
+        let mut set = InstructionSet::new();
+        set.register(CustomOp::new(20, "AND", 3, Some(2), |values| {
+            Some(values[0] & values[1])
+        }))
+        .unwrap();
+
+        let source = "IN [5]\nand [5], #12, [5]\nHLT";
+        let memory = assemble_with(source, Some(&set)).unwrap();
+        assert_eq!(memory.to_vec(), vec![3, 5, 1020, 5, 12, 5, 99]);
+        let listing = disassemble_with(&memory, Some(&set));
+        let round_trip = assemble_with(&listing, Some(&set)).unwrap();
+        assert_eq!(round_trip.to_vec(), memory.to_vec());
+
+        assert_eq!(
+            assemble_with("AND #1, #2, #3", Some(&set)).map(|m| m.to_vec()),
+            Err(AssembleError {
+                line: 1,
+                column: 13,
+                message: "cannot write to an immediate parameter".to_string(),
+            })
+        );
+        assert!(assemble(source).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |line, column, message: &str| {
+            Err(AssembleError {
+                line,
+                column,
+                message: message.to_string(),
+            })
+        };
+
+        assert_eq!(
+            assemble("HLT\n  FOO #1").map(|m| m.to_vec()),
+            error(2, 3, "unknown mnemonic `FOO`")
+        );
+        assert_eq!(
+            assemble("ADD #1, #2, #3").map(|m| m.to_vec()),
+            error(1, 13, "cannot write to an immediate parameter")
+        );
+        assert_eq!(
+            assemble("JT #1, nowhere").map(|m| m.to_vec()),
+            error(1, 8, "undefined label `nowhere`")
+        );
+        assert_eq!(
+            assemble("OUT #1, #2").map(|m| m.to_vec()),
+            error(1, 7, "`OUT` takes 1 parameters")
+        );
+        assert_eq!(
+            assemble("a: HLT\na: HLT").map(|m| m.to_vec()),
+            error(2, 1, "label `a` already defined")
+        );
+        assert_eq!(
+            assemble("OUT [rel 2]").map(|m| m.to_vec()),
+            error(1, 10, "expected `+` or `-` after `rel`")
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use super::{instruction::Instruction, memory::Memory, Word};
+
+/// Pointers past this are decoded on every visit instead of cached.
+const MAX_CACHED_POINTER: usize = 1 << 16;
+
+/// Decoded instructions keyed by pointer, each with the cells it was
+/// decoded from. An entry is only used while memory still holds those
+/// cells, so writes never have to touch the cache and self-modifying code
+/// still sees its own changes.
+///
+/// Clones of a machine share the entries. Entries are only added while the
+/// table isn't shared, so a template should be `predecode`d before it is
+/// cloned.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DecodeCache {
+    entries: Arc<Vec<Option<Entry>>>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    instruction: Instruction,
+    cells: Box<[Word]>,
+}
+
+impl DecodeCache {
+    pub(crate) fn get(&self, memory: &Memory, pointer: usize) -> Option<&Instruction> {
+        let entry = self.entries.get(pointer)?.as_ref()?;
+        let current = (pointer..pointer + entry.cells.len()).map(|address| memory.get(address));
+        entry
+            .cells
+            .iter()
+            .map(Some)
+            .eq(current)
+            .then_some(&entry.instruction)
+    }
+
+    pub(crate) fn insert(&mut self, memory: &Memory, pointer: usize, instruction: Instruction) {
+        if pointer >= MAX_CACHED_POINTER {
+            return;
+        }
+        let Some(entries) = Arc::get_mut(&mut self.entries) else {
+            return;
+        };
+        if entries.len() <= pointer {
+            entries.resize(pointer + 1, None);
+        }
+        let cells = memory
+            .iter_from(pointer)
+            .take(instruction.size())
+            .copied()
+            .collect();
+        entries[pointer] = Some(Entry { instruction, cells });
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{
+    instruction::{Instruction, Param},
+    instruction_set::InstructionSet,
+    memory::Memory,
+};
+
+/// The basic blocks reachable from address 0, in address order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cfg {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    /// Empty if the cell at `start` doesn't decode.
+    pub instructions: Vec<(usize, Instruction)>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    /// Falls through, or a conditional jump is not taken.
+    Next(usize),
+    /// An immediate-mode jump target.
+    Jump(usize),
+    /// A jump through a position or relative parameter.
+    Unknown,
+}
+
+/// Walks `memory` from address 0, following fall-through and immediate
+/// jump targets. Jumps whose condition is an immediate only get the edge
+/// that can actually be taken, so `JT #1, L` is an unconditional jump.
+pub fn control_flow(memory: &Memory) -> Cfg {
+    control_flow_with(memory, None)
+}
+
+/// Like `control_flow`, also decoding the custom opcodes in `set`, which
+/// fall through to the next instruction.
+pub fn control_flow_with(memory: &Memory, set: Option<&InstructionSet>) -> Cfg {
+    let mut instructions: BTreeMap<usize, Option<Instruction>> = BTreeMap::new();
+    let mut leaders = BTreeSet::from([0]);
+    let mut pending = vec![0];
+    while let Some(address) = pending.pop() {
+        if instructions.contains_key(&address) {
+            continue;
+        }
+        let Some(instruction) = decode(memory, address, set) else {
+            instructions.insert(address, None);
+            continue;
+        };
+
+        let edges = edges(address, &instruction);
+        let branches = is_branch(&instruction);
+        for edge in edges.iter() {
+            if let Edge::Next(target) | Edge::Jump(target) = *edge {
+                if branches {
+                    leaders.insert(target);
+                }
+                pending.push(target);
+            }
+        }
+        instructions.insert(address, Some(instruction));
+    }
+    // a cell that doesn't decode is shown as a block of its own
+    leaders.extend(
+        instructions
+            .iter()
+            .filter(|(_, instruction)| instruction.is_none())
+            .map(|(address, _)| *address),
+    );
+
+    let blocks = leaders
+        .iter()
+        .map(|&start| {
+            let mut block = Block {
+                start,
+                instructions: vec![],
+                edges: vec![],
+            };
+            let mut address = start;
+            while let Some(Some(instruction)) = instructions.get(&address) {
+                block.instructions.push((address, instruction.clone()));
+                block.edges = edges(address, instruction);
+                match block.edges.as_slice() {
+                    [Edge::Next(next)] if !leaders.contains(next) => address = *next,
+                    _ => break,
+                }
+            }
+            block
+        })
+        .collect();
+
+    Cfg { blocks }
+}
+
+fn decode(memory: &Memory, address: usize, set: Option<&InstructionSet>) -> Option<Instruction> {
+    let instruction = Instruction::decode_with(memory, address, set).ok()?;
+    (address + instruction.size() <= memory.len()).then_some(instruction)
+}
+
+fn is_branch(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::JumpIfTrue(..) | Instruction::JumpIfFalse(..) | Instruction::Stop
+    )
+}
+
+fn edges(address: usize, instruction: &Instruction) -> Vec<Edge> {
+    let next = Edge::Next(address + instruction.size());
+    let (condition, target, jump_if) = match instruction {
+        Instruction::Stop => return vec![],
+        Instruction::JumpIfTrue(condition, target) => (condition, target, true),
+        Instruction::JumpIfFalse(condition, target) => (condition, target, false),
+        _ => return vec![next],
+    };
+
+    let jump = match target {
+        Param::Imm(target) => usize::try_from(*target).map_or(Edge::Unknown, Edge::Jump),
+        _ => Edge::Unknown,
+    };
+    match condition {
+        Param::Imm(value) if (*value != 0) == jump_if => vec![jump],
+        Param::Imm(_) => vec![next],
+        _ => vec![jump, next],
+    }
+}
+
+impl Cfg {
+    /// Renders the graph in Graphviz DOT. Unknown edges point at a dashed
+    /// `?` node of their own.
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec![
+            "digraph intcode {".to_string(),
+            "    node [shape=box, fontname=\"monospace\"];".to_string(),
+        ];
+        for block in self.blocks.iter() {
+            let label = if block.instructions.is_empty() {
+                format!("{}: invalid\\l", block.start)
+            } else {
+                block
+                    .instructions
+                    .iter()
+                    .map(|(address, instruction)| format!("{}: {}\\l", address, instruction))
+                    .collect()
+            };
+            lines.push(format!("    b{} [label=\"{}\"];", block.start, label));
+
+            for edge in block.edges.iter() {
+                match edge {
+                    Edge::Next(target) => {
+                        lines.push(format!("    b{} -> b{};", block.start, target))
+                    }
+                    Edge::Jump(target) => lines.push(format!(
+                        "    b{} -> b{} [label=\"jump\"];",
+                        block.start, target
+                    )),
+                    Edge::Unknown => {
+                        lines.push(format!(
+                            "    u{} [label=\"?\", shape=circle, style=dashed];",
+                            block.start
+                        ));
+                        lines.push(format!(
+                            "    b{} -> u{} [style=dashed];",
+                            block.start, block.start
+                        ));
+                    }
+                }
+            }
+        }
+        lines.push("}".to_string());
+
+        lines
+            .into_iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{control_flow, control_flow_with, Edge};
+    use crate::computer::{
+        instruction_set::{CustomOp, InstructionSet},
+        memory::Memory,
+    };
+
+    #[test]
+    fn test_blocks() {
+        let memory = Memory::new(vec![
+            3, 13, 1001, 13, -1, 13, 4, 13, 1005, 13, 2, 99, 0, 5, -7,
+        ]);
+        let cfg = control_flow(&memory);
+        let summary: Vec<_> = cfg
+            .blocks
+            .iter()
+            .map(|block| {
+                let addresses: Vec<_> = block.instructions.iter().map(|(a, _)| *a).collect();
+                (block.start, addresses, block.edges.clone())
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, vec![0], vec![Edge::Next(2)]),
+                (2, vec![2, 6, 8], vec![Edge::Jump(2), Edge::Next(11)]),
+                (11, vec![11], vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dot() {
+        // jumps to 6 or falls through to HLT; 6 jumps unconditionally into a
+        // cell that doesn't decode
+        let memory = Memory::new(vec![3, 6, 1005, 6, 6, 99, 1105, 1, 9, 42]);
+        let expected = "\
+digraph intcode {
+    node [shape=box, fontname=\"monospace\"];
+    b0 [label=\"0: IN [pos 6]\\l2: JT [pos 6], #6\\l\"];
+    b0 -> b6 [label=\"jump\"];
+    b0 -> b5;
+    b5 [label=\"5: HLT\\l\"];
+    b6 [label=\"6: JT #1, #9\\l\"];
+    b6 -> b9 [label=\"jump\"];
+    b9 [label=\"9: invalid\\l\"];
+}
+";
+        assert_eq!(control_flow(&memory).to_dot(), expected);
+
+        let memory = Memory::new(vec![3, 5, 6, 5, 5, 99]);
+        let dot = control_flow(&memory).to_dot();
+        assert!(dot.contains("    b0 -> u0 [style=dashed];\n"));
+    }
+
+    #[test]
+    fn test_custom_ops() {
+        let mut set = InstructionSet::new();
+        set.register(CustomOp::new(20, "NOP", 1, None, |_| None))
+            .unwrap();
+        let memory = Memory::new(vec![20, 0, 99]);
+        assert_eq!(control_flow(&memory).blocks[0].instructions, vec![]);
+        let cfg = control_flow_with(&memory, Some(&set));
+        let addresses: Vec<_> = cfg.blocks[0].instructions.iter().map(|(a, _)| *a).collect();
+        assert_eq!(addresses, vec![0, 2]);
+    }
+}
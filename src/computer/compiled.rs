@@ -0,0 +1,419 @@
+use std::{fmt, sync::Arc};
+
+use super::{
+    device::{InputDevice, OutputDevice},
+    error::ComputerError,
+    instruction::{to_address, Instruction, Param},
+    instruction_set::InstructionSet,
+    machine::Machine,
+    memory::Memory,
+    Computer, RunState, Word,
+};
+
+/// Addresses past this are always interpreted.
+const MAX_COMPILED_ADDRESS: usize = 1 << 16;
+
+type Exec = Box<dyn Fn(&mut Computer) -> Result<Effect, ComputerError> + Send + Sync>;
+
+struct Op {
+    size: usize,
+    exec: Exec,
+}
+
+enum Effect {
+    Next,
+    Wrote(usize),
+    NeedsInput,
+    Output(Word),
+    Halted,
+}
+
+/// Runs a program as one closure per address, with parameter modes and
+/// positions bound when the image is compiled.
+///
+/// Every address that decodes is compiled, so indirect jumps land on
+/// compiled code too. When the program (or `set_memory`) writes into the
+/// cells of a compiled instruction, that instruction is retired for this
+/// machine and its address runs on the interpreter from then on. Clones
+/// share the compiled code.
+#[derive(Clone)]
+pub struct CompiledComputer {
+    computer: Computer,
+    ops: Arc<Vec<Option<Op>>>,
+    /// The size of the largest compiled instruction, so a write can only
+    /// change instructions at most this many cells before it.
+    max_size: usize,
+    retired: Vec<bool>,
+}
+
+impl CompiledComputer {
+    pub fn new(memory: Memory) -> Self {
+        let mut computer = Computer::new(memory);
+        // the compiled ops already are the cache, fallbacks decode fresh
+        computer.set_decode_cache(false);
+        let mut compiled = Self {
+            computer,
+            ops: Arc::new(vec![]),
+            max_size: 0,
+            retired: vec![],
+        };
+        compiled.compile();
+        compiled
+    }
+
+    /// Compiles the opcodes registered in `set` as well as the standard
+    /// ones. The current memory image is compiled again, so this is best
+    /// done before running.
+    pub fn set_instruction_set(&mut self, set: InstructionSet) {
+        self.computer.set_instruction_set(set);
+        self.compile();
+    }
+
+    fn compile(&mut self) {
+        let memory = &self.computer.memory;
+        let set = self.computer.instruction_set();
+        let end = memory.len().min(MAX_COMPILED_ADDRESS);
+        let ops: Vec<Option<Op>> = (0..end)
+            .map(|pointer| compile(memory, pointer, set))
+            .collect();
+        self.retired = vec![false; ops.len()];
+        self.max_size = ops.iter().flatten().map(|op| op.size).max().unwrap_or(0);
+        self.ops = Arc::new(ops);
+    }
+
+    fn retire(&mut self, address: usize) {
+        let start = address.saturating_sub(self.max_size.saturating_sub(1));
+        let end = address.saturating_add(1).min(self.ops.len());
+        for pointer in start..end {
+            if matches!(&self.ops[pointer], Some(op) if pointer + op.size > address) {
+                self.retired[pointer] = true;
+            }
+        }
+    }
+
+    /// Runs the instruction at the pointer on the interpreter.
+    fn interpret(&mut self) -> Result<Option<RunState>, ComputerError> {
+        let step = self.computer.step()?;
+        if let Some(write) = step.as_ref().and_then(|step| step.write) {
+            self.retire(write.address);
+        }
+        Ok(self.computer.stop_state(step.as_ref()))
+    }
+}
+
+impl Machine for CompiledComputer {
+    fn set_yield_on_output(&mut self, val: bool) {
+        self.computer.set_yield_on_output(val)
+    }
+
+    fn set_input(&mut self, input: Vec<Word>) {
+        self.computer.set_input(input)
+    }
+
+    fn push_input(&mut self, input: Word) {
+        self.computer.push_input(input)
+    }
+
+    fn get_output(&self) -> Vec<Word> {
+        self.computer.get_output()
+    }
+
+    fn next_output(&mut self) -> Option<Word> {
+        self.computer.next_output()
+    }
+
+    fn set_input_device(&mut self, device: Box<dyn InputDevice + Send>) {
+        self.computer.set_input_device(device)
+    }
+
+    fn set_output_device(&mut self, device: Box<dyn OutputDevice + Send>) {
+        self.computer.set_output_device(device)
+    }
+
+    fn clear_input_device(&mut self) {
+        self.computer.clear_input_device()
+    }
+
+    fn clear_output_device(&mut self) {
+        self.computer.clear_output_device()
+    }
+
+    fn is_halted(&self) -> bool {
+        self.computer.is_halted()
+    }
+
+    fn memory(&self) -> &Memory {
+        self.computer.memory()
+    }
+
+    fn set_memory(&mut self, address: usize, value: Word) {
+        self.computer.set_memory(address, value);
+        self.retire(address);
+    }
+
+    fn set_instruction_set(&mut self, set: InstructionSet) {
+        CompiledComputer::set_instruction_set(self, set)
+    }
+
+    fn run(&mut self) -> Result<RunState, ComputerError> {
+        loop {
+            if self.computer.halted {
+                return Ok(RunState::Halted);
+            }
+
+            let pointer = self.computer.pointer;
+            let op = match self.ops.get(pointer) {
+                Some(Some(op)) if !self.retired[pointer] => op,
+                _ => match self.interpret()? {
+                    Some(state) => return Ok(state),
+                    None => continue,
+                },
+            };
+
+            match (op.exec)(&mut self.computer)? {
+                Effect::Next => {}
+                Effect::Wrote(address) => self.retire(address),
+                Effect::NeedsInput => return Ok(RunState::NeedsInput),
+                Effect::Output(value) => return Ok(RunState::Output(value)),
+                Effect::Halted => return Ok(RunState::Halted),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for CompiledComputer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompiledComputer")
+            .field("computer", &self.computer)
+            .field("ops", &self.ops.iter().flatten().count())
+            .finish()
+    }
+}
+
+/// A parameter with its mode resolved at compile time.
+#[derive(Clone, Copy)]
+enum Operand {
+    Imm(Word),
+    Pos(usize),
+    Rel(Word),
+}
+
+impl Operand {
+    /// `None` for negative positions, which are left to the interpreter to
+    /// report.
+    fn source(param: Param) -> Option<Self> {
+        match param {
+            Param::Imm(value) => Some(Self::Imm(value)),
+            Param::Pos(address) => usize::try_from(address).ok().map(Self::Pos),
+            Param::Rel(offset) => Some(Self::Rel(offset)),
+        }
+    }
+
+    /// Like `source`, but also `None` for immediates, which can't be written.
+    fn target(param: Param) -> Option<Self> {
+        match param {
+            Param::Imm(_) => None,
+            param => Self::source(param),
+        }
+    }
+
+    fn load(self, computer: &Computer) -> Result<Word, ComputerError> {
+        match self {
+            Self::Imm(value) => Ok(value),
+            _ => Ok(*computer.memory.get(self.address(computer)?).unwrap_or(&0)),
+        }
+    }
+
+    /// The address of a target, if it may be written.
+    fn write_address(self, computer: &Computer) -> Result<usize, ComputerError> {
+        let address = self.address(computer)?;
+        computer.check_write(address)?;
+        Ok(address)
+    }
+
+    fn address(self, computer: &Computer) -> Result<usize, ComputerError> {
+        match self {
+            Self::Pos(address) => Ok(address),
+            Self::Rel(offset) => Param::Rel(offset).as_pos(computer),
+            Self::Imm(_) => unreachable!("immediate targets are never compiled"),
+        }
+    }
+}
+
+/// `None` if the instruction should be left to the interpreter, either
+/// because it doesn't decode or because it always fails.
+fn compile(memory: &Memory, pointer: usize, set: Option<&InstructionSet>) -> Option<Op> {
+    let instruction = Instruction::decode_with(memory, pointer, set).ok()?;
+    let size = instruction.size();
+    let next = pointer + size;
+
+    let exec: Exec = match instruction {
+        Instruction::Add(p1, p2, p3) => binary(p1, p2, p3, next, Computer::add)?,
+        Instruction::Mult(p1, p2, p3) => binary(p1, p2, p3, next, Computer::mul)?,
+        Instruction::LessThan(p1, p2, p3) => {
+            binary(p1, p2, p3, next, |_, a, b| Ok(Word::from(a < b)))?
+        }
+        Instruction::Equals(p1, p2, p3) => {
+            binary(p1, p2, p3, next, |_, a, b| Ok(Word::from(a == b)))?
+        }
+        Instruction::Input(p1) => {
+            let target = Operand::target(p1)?;
+            Box::new(move |computer: &mut Computer| {
+                let address = target.write_address(computer)?;
+                let Some(value) = computer.read_input() else {
+                    return Ok(Effect::NeedsInput);
+                };
+                computer.set_memory(address, value);
+                computer.pointer = next;
+                Ok(Effect::Wrote(address))
+            })
+        }
+        Instruction::Output(p1) => {
+            let source = Operand::source(p1)?;
+            Box::new(move |computer: &mut Computer| {
+                let value = source.load(computer)?;
+                computer.pointer = next;
+                if computer.yeild_on_output {
+                    Ok(Effect::Output(value))
+                } else {
+                    computer.write_output(value);
+                    Ok(Effect::Next)
+                }
+            })
+        }
+        Instruction::JumpIfTrue(p1, p2) => jump(p1, p2, next, |value| value != 0)?,
+        Instruction::JumpIfFalse(p1, p2) => jump(p1, p2, next, |value| value == 0)?,
+        Instruction::RelativeBase(p1) => {
+            let source = Operand::source(p1)?;
+            Box::new(move |computer: &mut Computer| {
+                let value = source.load(computer)?;
+                computer.relative_base = computer.add(computer.relative_base, value)?;
+                computer.pointer = next;
+                Ok(Effect::Next)
+            })
+        }
+        Instruction::Custom {
+            code,
+            params,
+            write,
+            ..
+        } => {
+            let op = set?.get(code)?.clone();
+            let target = match write {
+                Some(index) => Some(Operand::target(params[index].clone())?),
+                None => None,
+            };
+            let sources = params
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| Some(*index) != write)
+                .map(|(_, param)| Operand::source(param))
+                .collect::<Option<Vec<_>>>()?;
+            Box::new(move |computer: &mut Computer| {
+                let operands = sources
+                    .iter()
+                    .map(|source| source.load(computer))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let effect = match (target, op.execute(&operands)) {
+                    (Some(target), Some(value)) => {
+                        let address = target.write_address(computer)?;
+                        computer.set_memory(address, value);
+                        Effect::Wrote(address)
+                    }
+                    _ => Effect::Next,
+                };
+                computer.pointer = next;
+                Ok(effect)
+            })
+        }
+        Instruction::Stop => Box::new(|computer: &mut Computer| {
+            computer.halted = true;
+            Ok(Effect::Halted)
+        }),
+    };
+
+    Some(Op { size, exec })
+}
+
+fn binary(
+    p1: Param,
+    p2: Param,
+    p3: Param,
+    next: usize,
+    op: fn(&Computer, Word, Word) -> Result<Word, ComputerError>,
+) -> Option<Exec> {
+    let (a, b) = (Operand::source(p1)?, Operand::source(p2)?);
+    let target = Operand::target(p3)?;
+    Some(Box::new(move |computer: &mut Computer| {
+        let value = op(computer, a.load(computer)?, b.load(computer)?)?;
+        let address = target.write_address(computer)?;
+        computer.set_memory(address, value);
+        computer.pointer = next;
+        Ok(Effect::Wrote(address))
+    }))
+}
+
+fn jump(p1: Param, p2: Param, next: usize, taken: fn(Word) -> bool) -> Option<Exec> {
+    let (condition, target) = (Operand::source(p1)?, Operand::source(p2)?);
+    Some(Box::new(move |computer: &mut Computer| {
+        if !taken(condition.load(computer)?) {
+            computer.pointer = next;
+            return Ok(Effect::Next);
+        }
+        let address = target.load(computer)?;
+        computer.pointer = to_address(address, computer.pointer)?;
+        Ok(Effect::Next)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompiledComputer;
+    use crate::computer::{error::ComputerError, machine::Machine, Computer, RunState};
+
+    #[test]
+    fn test_matches_interpreter() {
+        let programs = [
+            "3,9,8,9,10,9,4,9,99,-1,8",
+            "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
+            "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+            "1,-3,0,0,99",
+            "3,0,4,0,4,0,99",
+            "1101,1,1,1000000000000000,99",
+            "3,1000000000000000,99",
+        ];
+        for program in programs {
+            let mut interpreted = Computer::new(program.parse().unwrap());
+            let mut compiled = CompiledComputer::new(program.parse().unwrap());
+            for machine in [&mut interpreted as &mut dyn Machine, &mut compiled] {
+                machine.push_input(8);
+            }
+            assert_eq!(compiled.run(), interpreted.run());
+            assert_eq!(compiled.get_output(), interpreted.get_output());
+            assert_eq!(compiled.memory().to_vec(), interpreted.memory().to_vec());
+        }
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // runs the instruction at 0 twice, patching it from ADD to MUL in between
+        let program = "1,20,21,20,1005,22,18,1101,0,2,0,1101,0,1,22,1105,1,0,99,0,3,4,0";
+        let template = CompiledComputer::new(program.parse().unwrap());
+        for mut computer in [template.clone(), template] {
+            assert_eq!(computer.run(), Ok(RunState::Halted));
+            assert_eq!(computer.memory()[20], 28);
+        }
+
+        let mut computer = CompiledComputer::new("1101,1,1,3,99".parse().unwrap());
+        computer.set_memory(3, 0);
+        computer.set_memory(0, 1102);
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.memory().to_vec(), vec![1, 1, 1, 0, 99]);
+
+        let mut computer = CompiledComputer::new("1101,0,1,0,11101,1,1,0".parse().unwrap());
+        assert_eq!(
+            computer.run(),
+            Err(ComputerError::WriteToImmediate { pointer: 4 })
+        );
+    }
+}
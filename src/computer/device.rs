@@ -0,0 +1,315 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, BufRead},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+};
+
+use super::Word;
+
+/// Where a machine's `Input` instructions read from.
+pub trait InputDevice {
+    /// `None` if no value is available yet, which stops the machine with
+    /// `RunState::NeedsInput`.
+    fn read(&mut self) -> Option<Word>;
+}
+
+/// Where a machine's `Output` instructions write to.
+pub trait OutputDevice {
+    fn write(&mut self, value: Word);
+}
+
+impl<D: InputDevice + ?Sized> InputDevice for Box<D> {
+    fn read(&mut self) -> Option<Word> {
+        (**self).read()
+    }
+}
+
+impl<D: OutputDevice + ?Sized> OutputDevice for Box<D> {
+    fn write(&mut self, value: Word) {
+        (**self).write(value)
+    }
+}
+
+/// A scripted source: values are read front to back.
+impl InputDevice for VecDeque<Word> {
+    fn read(&mut self) -> Option<Word> {
+        self.pop_front()
+    }
+}
+
+impl OutputDevice for VecDeque<Word> {
+    fn write(&mut self, value: Word) {
+        self.push_back(value);
+    }
+}
+
+impl OutputDevice for Vec<Word> {
+    fn write(&mut self, value: Word) {
+        self.push(value);
+    }
+}
+
+/// Blocks until a value arrives. Reads `None` once every sender is gone.
+impl InputDevice for Receiver<Word> {
+    fn read(&mut self) -> Option<Word> {
+        self.recv().ok()
+    }
+}
+
+/// Values sent after the receiver is gone are dropped.
+impl OutputDevice for Sender<Word> {
+    fn write(&mut self, value: Word) {
+        let _ = self.send(value);
+    }
+}
+
+/// Reads input from a closure.
+pub struct InputFn<F>(pub F);
+
+impl<F: FnMut() -> Option<Word>> InputDevice for InputFn<F> {
+    fn read(&mut self) -> Option<Word> {
+        (self.0)()
+    }
+}
+
+/// Sends output to a closure.
+pub struct OutputFn<F>(pub F);
+
+impl<F: FnMut(Word)> OutputDevice for OutputFn<F> {
+    fn write(&mut self, value: Word) {
+        (self.0)(value)
+    }
+}
+
+/// Parses whitespace or comma separated numbers from a reader, such as
+/// locked stdin.
+pub struct TextInput<R: BufRead> {
+    reader: R,
+    pending: VecDeque<Word>,
+    error: Option<String>,
+}
+
+impl<R: BufRead> TextInput<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            pending: VecDeque::new(),
+            error: None,
+        }
+    }
+
+    /// The first read or parse error, if any. Input ends once one happens.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+impl<R: BufRead> InputDevice for TextInput<R> {
+    fn read(&mut self) -> Option<Word> {
+        while self.pending.is_empty() && self.error.is_none() {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => {
+                    self.error = Some(err.to_string());
+                    return None;
+                }
+            }
+            for token in line.split(|c: char| c == ',' || c.is_whitespace()) {
+                if token.is_empty() {
+                    continue;
+                }
+                match token.parse() {
+                    Ok(value) => self.pending.push_back(value),
+                    Err(_) => {
+                        self.error = Some(format!("invalid input `{}`", token));
+                        break;
+                    }
+                }
+            }
+        }
+        self.pending.pop_front()
+    }
+}
+
+/// Writes one value per line.
+pub struct TextOutput<W: io::Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> TextOutput<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    /// The first write error, if any. Output is dropped once writing fails.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: io::Write> OutputDevice for TextOutput<W> {
+    fn write(&mut self, value: Word) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(err) = writeln!(self.writer, "{}", value) {
+            self.error = Some(err);
+        }
+    }
+}
+
+/// A queue shared by its clones, for connecting one machine's output to
+/// another's input on the same thread. Reading an empty pipe reads `None`.
+#[derive(Clone, Debug, Default)]
+pub struct Pipe(Arc<Mutex<VecDeque<Word>>>);
+
+impl Pipe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn queue(&self) -> MutexGuard<'_, VecDeque<Word>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn push(&self, value: Word) {
+        self.queue().push_back(value);
+    }
+
+    pub fn pop(&self) -> Option<Word> {
+        self.queue().pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue().is_empty()
+    }
+
+    pub fn to_vec(&self) -> Vec<Word> {
+        self.queue().iter().copied().collect()
+    }
+}
+
+impl From<Vec<Word>> for Pipe {
+    fn from(values: Vec<Word>) -> Self {
+        Self(Arc::new(Mutex::new(values.into())))
+    }
+}
+
+impl InputDevice for Pipe {
+    fn read(&mut self) -> Option<Word> {
+        self.pop()
+    }
+}
+
+impl OutputDevice for Pipe {
+    fn write(&mut self, value: Word) {
+        self.push(value);
+    }
+}
+
+/// A device attached to a computer. Clones of the computer share it.
+pub(crate) struct Device<D: ?Sized>(Arc<Mutex<D>>);
+
+impl Device<dyn InputDevice + Send> {
+    pub fn input<D: InputDevice + Send + 'static>(device: D) -> Self {
+        Self(Arc::new(Mutex::new(device)))
+    }
+
+    pub fn read(&self) -> Option<Word> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).read()
+    }
+}
+
+impl Device<dyn OutputDevice + Send> {
+    pub fn output<D: OutputDevice + Send + 'static>(device: D) -> Self {
+        Self(Arc::new(Mutex::new(device)))
+    }
+
+    pub fn write(&self, value: Word) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write(value)
+    }
+}
+
+impl<D: ?Sized> Clone for Device<D> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<D: ?Sized> fmt::Debug for Device<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Device")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::{InputFn, OutputDevice, Pipe, TextInput, TextOutput};
+    use crate::computer::{Computer, RunState};
+
+    const DOUBLER: &str = "3,11,102,2,11,11,4,11,1105,1,0,0";
+
+    #[test]
+    fn test_queue_input_first() {
+        let mut computer = Computer::new(DOUBLER.parse().unwrap());
+        let mut next = 10;
+        computer.set_input_device(InputFn(move || {
+            next += 1;
+            (next <= 12).then_some(next)
+        }));
+        computer.push_input(1);
+        assert_eq!(computer.run(), Ok(RunState::NeedsInput));
+        assert_eq!(computer.get_output(), vec![2, 22, 24]);
+    }
+
+    #[test]
+    fn test_devices() {
+        let (sender, receiver) = mpsc::channel();
+        let mut computer = Computer::new(DOUBLER.parse().unwrap());
+        computer.set_input_device(TextInput::new("1, 2\n\n3 x 4\n".as_bytes()));
+        computer.set_output_device(sender.clone());
+        assert_eq!(computer.run(), Ok(RunState::NeedsInput));
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![2, 4, 6]);
+
+        let between = Pipe::new();
+        let mut first = Computer::new(DOUBLER.parse().unwrap());
+        first.set_input_device(Pipe::from(vec![5, 6]));
+        first.set_output_device(between.clone());
+        let mut second = Computer::new(DOUBLER.parse().unwrap());
+        second.set_input_device(between.clone());
+        second.set_output_device(sender);
+        assert_eq!(first.run(), Ok(RunState::NeedsInput));
+        assert_eq!(between.to_vec(), vec![10, 12]);
+        assert_eq!(second.run(), Ok(RunState::NeedsInput));
+        assert!(between.is_empty());
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![20, 24]);
+
+        let mut output = TextOutput::new(vec![]);
+        output.write(3);
+        output.write(-4);
+        assert_eq!(output.into_inner(), b"3\n-4\n");
+    }
+}
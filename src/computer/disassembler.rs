@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+
+use super::{
+    instruction::{Instruction, Param},
+    instruction_set::InstructionSet,
+    memory::Memory,
+    Word,
+};
+
+/// Data cells listed per `.data` line.
+const DATA_PER_LINE: usize = 8;
+
+enum Item {
+    Code(Instruction),
+    Data(Word),
+    /// Cells of sparse memory that were never written, up to this address.
+    Unallocated(usize),
+}
+
+/// Produces a mnemonic listing of a memory image. The image is swept
+/// linearly from address 0; cells that do not decode to a complete
+/// instruction are listed as `.data`. Immediate jump targets that land on
+/// the start of an item get a label. Unwritten pages of sparse memory are
+/// skipped, with a comment in their place.
+pub fn disassemble(memory: &Memory) -> String {
+    disassemble_with(memory, None)
+}
+
+/// Like `disassemble`, also decoding the custom opcodes in `set`.
+pub fn disassemble_with(memory: &Memory, set: Option<&InstructionSet>) -> String {
+    let items = decode(memory, set);
+    let labels = labels(&items);
+
+    let mut lines = vec![];
+    let mut data: Vec<(usize, Word)> = vec![];
+    for (address, item) in items.iter() {
+        let label = labels.get(address);
+        if label.is_some() || !matches!(item, Item::Data(_)) || data.len() == DATA_PER_LINE {
+            flush_data(&mut data, &mut lines);
+        }
+        if let Some(label) = label {
+            lines.push(format!("{}:", label));
+        }
+
+        match item {
+            Item::Code(instruction) => {
+                lines.push(format!(
+                    "{:>6}  {}",
+                    address,
+                    format_instruction(instruction, &labels)
+                ));
+            }
+            Item::Data(value) => data.push((*address, *value)),
+            Item::Unallocated(end) => {
+                lines.push(format!("{:>6}  ; unallocated up to {}", address, end));
+            }
+        }
+    }
+    flush_data(&mut data, &mut lines);
+
+    lines
+        .into_iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+fn decode(memory: &Memory, set: Option<&InstructionSet>) -> Vec<(usize, Item)> {
+    let mut items = vec![];
+    let mut address = 0;
+    while address < memory.len() {
+        let next = memory.next_allocated(address);
+        if next > address {
+            items.push((address, Item::Unallocated(next)));
+            address = next;
+            continue;
+        }
+        match Instruction::decode_with(memory, address, set) {
+            Ok(instruction) if address + instruction.size() <= memory.len() => {
+                let size = instruction.size();
+                items.push((address, Item::Code(instruction)));
+                address += size;
+            }
+            _ => {
+                items.push((address, Item::Data(memory[address])));
+                address += 1;
+            }
+        }
+    }
+    items
+}
+
+fn labels(items: &[(usize, Item)]) -> BTreeMap<usize, String> {
+    let mut targets: BTreeMap<usize, String> = items
+        .iter()
+        .filter_map(|(_, item)| match item {
+            Item::Code(instruction) => jump_target(instruction),
+            Item::Data(_) | Item::Unallocated(_) => None,
+        })
+        .filter(|target| {
+            items
+                .binary_search_by_key(target, |(address, _)| *address)
+                .is_ok()
+        })
+        .map(|target| (target, String::new()))
+        .collect();
+
+    for (index, label) in targets.values_mut().enumerate() {
+        *label = format!("L{}", index);
+    }
+    targets
+}
+
+fn jump_target(instruction: &Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::JumpIfTrue(_, Param::Imm(target))
+        | Instruction::JumpIfFalse(_, Param::Imm(target)) => usize::try_from(*target).ok(),
+        _ => None,
+    }
+}
+
+fn format_instruction(instruction: &Instruction, labels: &BTreeMap<usize, String>) -> String {
+    let label = jump_target(instruction).and_then(|target| labels.get(&target));
+    match (instruction, label) {
+        (Instruction::JumpIfTrue(p1, _) | Instruction::JumpIfFalse(p1, _), Some(label)) => {
+            format!("{} {}, {}", instruction.mnemonic(), p1, label)
+        }
+        _ => instruction.to_string(),
+    }
+}
+
+fn flush_data(data: &mut Vec<(usize, Word)>, lines: &mut Vec<String>) {
+    if let Some((address, _)) = data.first() {
+        let values = data
+            .iter()
+            .map(|(_, value)| value.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        lines.push(format!("{:>6}  .data {}", address, values));
+    }
+    data.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{disassemble, disassemble_with, DATA_PER_LINE};
+    use crate::computer::{
+        instruction_set::{CustomOp, InstructionSet},
+        memory::{Memory, PAGE_SIZE},
+    };
+
+    #[test]
+    fn test_disassemble() {
+        let memory = Memory::new(vec![
+            3, 13, 1001, 13, -1, 13, 4, 13, 1005, 13, 2, 99, 0, 5, -7,
+        ]);
+        let expected = [
+            "     0  IN [pos 13]",
+            "L0:",
+            "     2  ADD [pos 13], #-1, [pos 13]",
+            "     6  OUT [pos 13]",
+            "     8  JT [pos 13], L0",
+            "    11  HLT",
+            "    12  .data 0, 5, -7",
+        ];
+        assert_eq!(disassemble(&memory).lines().collect::<Vec<_>>(), expected);
+
+        let mut set = InstructionSet::new();
+        set.register(CustomOp::new(20, "AND", 3, Some(2), |values| {
+            Some(values[0] & values[1])
+        }))
+        .unwrap();
+        let memory = Memory::new(vec![1120, 12, 10, 5, 99]);
+        assert_eq!(
+            disassemble(&memory),
+            "     0  .data 1120, 12, 10, 5\n     4  HLT\n"
+        );
+        assert_eq!(
+            disassemble_with(&memory, Some(&set)),
+            "     0  AND #12, #10, [pos 5]\n     4  HLT\n"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_truncated() {
+        let listing = disassemble(&Memory::new(vec![1101, 1, 2]));
+        assert_eq!(listing, "     0  .data 1101, 1, 2\n");
+
+        let mut memory = Memory::sparse(vec![99]);
+        memory.set(1 << 40, 7);
+        let listing = disassemble(&memory);
+        let lines: Vec<_> = listing.lines().collect();
+        assert_eq!(lines.len(), 3 + PAGE_SIZE / DATA_PER_LINE);
+        assert_eq!(lines[1], "     1  .data 0, 0, 0, 0, 0, 0, 0, 0");
+        assert_eq!(
+            lines[PAGE_SIZE / DATA_PER_LINE + 1],
+            "  4096  ; unallocated up to 1099511627776"
+        );
+        assert_eq!(lines.last(), Some(&"1099511627776  .data 7"));
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt;
+
+use super::Word;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComputerError {
+    InvalidOpcode {
+        opcode: Word,
+        pointer: usize,
+    },
+    UnknownParamMode {
+        mode: usize,
+        pointer: usize,
+    },
+    PointerOutOfBounds {
+        pointer: usize,
+    },
+    NegativeAddress {
+        address: Word,
+        pointer: usize,
+    },
+    WriteToImmediate {
+        pointer: usize,
+    },
+    Overflow {
+        pointer: usize,
+    },
+    /// A non-negative address too large for this platform's memory, or a
+    /// write past the dense memory limit.
+    AddressOutOfRange {
+        address: Word,
+        pointer: usize,
+    },
+}
+
+impl fmt::Display for ComputerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidOpcode { opcode, pointer } => {
+                write!(f, "invalid opcode {} at address {}", opcode, pointer)
+            }
+            Self::UnknownParamMode { mode, pointer } => {
+                write!(f, "unknown parameter mode {} at address {}", mode, pointer)
+            }
+            Self::PointerOutOfBounds { pointer } => {
+                write!(f, "instruction pointer {} is outside of memory", pointer)
+            }
+            Self::NegativeAddress { address, pointer } => {
+                write!(
+                    f,
+                    "negative address {} used by instruction at address {}",
+                    address, pointer
+                )
+            }
+            Self::WriteToImmediate { pointer } => {
+                write!(
+                    f,
+                    "immediate mode parameter used as a write target at address {}",
+                    pointer
+                )
+            }
+            Self::Overflow { pointer } => {
+                write!(f, "arithmetic overflow at address {}", pointer)
+            }
+            Self::AddressOutOfRange { address, pointer } => {
+                write!(
+                    f,
+                    "address {} used by instruction at address {} is out of range",
+                    address, pointer
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ComputerError {}
@@ -0,0 +1,406 @@
+use std::fmt;
+
+use super::{memory::Memory, Computer, Word};
+
+/// Opcodes the generator picks from, `99` excluded.
+const OPCODES: [Word; 9] = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+/// Random values after the code, for position and relative reads to hit.
+const DATA_CELLS: usize = 8;
+/// Cases that write past this are skipped rather than allocating the memory.
+const MAX_ADDRESS: usize = 1 << 16;
+
+/// xorshift64*, so runs can be reproduced from their seed.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift never leaves the all-zero state
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A value in `low..high`.
+    pub fn range(&mut self, low: Word, high: Word) -> Word {
+        low + (self.next_u64() % (high - low) as u64) as Word
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.range(0, items.len() as Word) as usize]
+    }
+}
+
+/// A program and the input queued before it starts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Case {
+    pub program: Vec<Word>,
+    pub input: Vec<Word>,
+}
+
+impl Case {
+    /// Straight-line code ending in `99`, followed by data. Parameter modes
+    /// are random, but writes never use immediate mode, and immediate jump
+    /// targets are the start of an instruction.
+    pub fn generate(rng: &mut Rng) -> Self {
+        let opcodes: Vec<Word> = (0..rng.range(1, 24)).map(|_| rng.pick(&OPCODES)).collect();
+        let mut starts = vec![0];
+        for opcode in &opcodes {
+            starts.push(starts.last().unwrap() + 1 + params(*opcode));
+        }
+        let len = (starts.last().unwrap() + 1 + DATA_CELLS) as Word;
+
+        let mut program = vec![];
+        for opcode in opcodes {
+            let mut modes = 0;
+            let mut values = vec![];
+            for index in 0..params(opcode) {
+                let writes = index == 2 || opcode == 3;
+                let mode = rng.pick(if writes { &[0, 2][..] } else { &[0, 1, 2] });
+                let value = match mode {
+                    0 => rng.range(0, len + 4),
+                    2 => rng.range(-4, len + 4),
+                    _ if index == 1 && (opcode == 5 || opcode == 6) => rng.pick(&starts) as Word,
+                    _ if opcode == 9 => rng.range(-3, 4),
+                    _ => rng.range(-10, 11),
+                };
+                modes += mode * (10 as Word).pow(index as u32 + 2);
+                values.push(value);
+            }
+            program.push(opcode + modes);
+            program.extend(values);
+        }
+        program.push(99);
+        program.extend((0..DATA_CELLS).map(|_| rng.range(-10, 11)));
+
+        let input = (0..rng.range(0, 4)).map(|_| rng.range(-10, 11)).collect();
+        Self { program, input }
+    }
+}
+
+fn params(opcode: Word) -> usize {
+    match opcode {
+        1 | 2 | 7 | 8 => 3,
+        5 | 6 => 2,
+        3 | 4 | 9 => 1,
+        _ => 0,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub memory: Vec<Word>,
+    pub output: Vec<Word>,
+    pub end: End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum End {
+    Halted,
+    NeedsInput,
+    /// Ran out of steps before stopping.
+    StepLimit,
+    /// Stopped with an error in the instruction at `pointer`.
+    Error {
+        pointer: usize,
+    },
+}
+
+/// A case where `Computer` and the reference evaluator disagree.
+#[derive(Clone, Debug)]
+pub struct Mismatch {
+    pub case: Case,
+    pub expected: Outcome,
+    pub actual: Outcome,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "program:   {}", Memory::new(self.case.program.clone()))?;
+        writeln!(f, "input:     {:?}", self.case.input)?;
+        writeln!(f, "reference: {:?}", self.expected)?;
+        write!(f, "computer:  {:?}", self.actual)
+    }
+}
+
+/// Runs `case` on both evaluators for at most `limit` instructions.
+pub fn check(case: &Case, limit: usize) -> Option<Mismatch> {
+    let expected = reference(case, limit)?;
+    let actual = run_computer(case, limit);
+    (expected != actual).then(|| Mismatch {
+        case: case.clone(),
+        expected,
+        actual,
+    })
+}
+
+/// Checks `runs` generated cases, and minimizes the first mismatch.
+pub fn fuzz(seed: u64, runs: usize, limit: usize) -> Option<Mismatch> {
+    let mut rng = Rng::new(seed);
+    let case = (0..runs)
+        .map(|_| Case::generate(&mut rng))
+        .find(|case| check(case, limit).is_some())?;
+    let case = minimize(case, |case| check(case, limit).is_some());
+    check(&case, limit)
+}
+
+/// Greedily drops input values and program cells, then zeroes cells, for
+/// as long as `fails` still holds.
+pub fn minimize(mut case: Case, fails: impl Fn(&Case) -> bool) -> Case {
+    loop {
+        let candidates = (0..case.input.len())
+            .map(|index| {
+                let mut smaller = case.clone();
+                smaller.input.remove(index);
+                smaller
+            })
+            .chain((0..case.program.len()).map(|index| {
+                let mut smaller = case.clone();
+                smaller.program.remove(index);
+                smaller
+            }))
+            .chain(
+                (0..case.program.len())
+                    .filter(|&index| case.program[index] != 0)
+                    .map(|index| {
+                        let mut smaller = case.clone();
+                        smaller.program[index] = 0;
+                        smaller
+                    }),
+            );
+        match candidates.into_iter().find(|candidate| fails(candidate)) {
+            Some(smaller) => case = smaller,
+            None => return case,
+        }
+    }
+}
+
+fn run_computer(case: &Case, limit: usize) -> Outcome {
+    let mut computer = Computer::new(Memory::new(case.program.clone()));
+    computer.set_input(case.input.clone());
+    let mut end = End::StepLimit;
+    for _ in 0..limit {
+        match computer.step() {
+            Ok(Some(_)) if computer.is_halted() => end = End::Halted,
+            Ok(Some(_)) => continue,
+            Ok(None) => end = End::NeedsInput,
+            Err(_) => {
+                end = End::Error {
+                    pointer: computer.pointer(),
+                }
+            }
+        }
+        break;
+    }
+    Outcome {
+        memory: computer.memory().to_vec(),
+        output: computer.get_output(),
+        end,
+    }
+}
+
+/// An evaluator written from the spec that shares no code with `Computer`,
+/// kept as simple as possible so that it is easy to trust. `None` if the
+/// case writes past `MAX_ADDRESS`.
+fn reference(case: &Case, limit: usize) -> Option<Outcome> {
+    let mut machine = Reference {
+        memory: case.program.clone(),
+        pointer: 0,
+        base: 0,
+        too_large: false,
+    };
+    let mut input = case.input.iter();
+    let mut output = vec![];
+    let mut end = End::StepLimit;
+    for _ in 0..limit {
+        match machine.step(&mut input, &mut output) {
+            Ok(None) => continue,
+            Ok(Some(stop)) => end = stop,
+            Err(()) if machine.too_large => return None,
+            Err(()) => {
+                end = End::Error {
+                    pointer: machine.pointer,
+                }
+            }
+        }
+        break;
+    }
+    Some(Outcome {
+        memory: machine.memory,
+        output,
+        end,
+    })
+}
+
+/// Results must fit in 64 bits, as with `Computer`'s default arithmetic.
+fn fits(value: Option<Word>) -> Result<Word, ()> {
+    value
+        .filter(|value| i64::try_from(*value).is_ok())
+        .ok_or(())
+}
+
+struct Reference {
+    memory: Vec<Word>,
+    pointer: usize,
+    base: Word,
+    /// Set when a write goes past `MAX_ADDRESS`.
+    too_large: bool,
+}
+
+impl Reference {
+    fn step(
+        &mut self,
+        input: &mut std::slice::Iter<Word>,
+        output: &mut Vec<Word>,
+    ) -> Result<Option<End>, ()> {
+        let op = *self.memory.get(self.pointer).ok_or(())?;
+        if op < 0 {
+            return Err(());
+        }
+        let mut modes = op / 100;
+        while modes > 0 {
+            if modes % 10 > 2 {
+                return Err(());
+            }
+            modes /= 10;
+        }
+
+        match op % 100 {
+            1 | 2 => {
+                let (a, b) = (self.load(0)?, self.load(1)?);
+                let value = if op % 100 == 1 {
+                    a.checked_add(b)
+                } else {
+                    a.checked_mul(b)
+                };
+                self.store(2, fits(value)?)?;
+                self.pointer += 4;
+            }
+            3 => {
+                self.address(0)?;
+                let Some(&value) = input.next() else {
+                    return Ok(Some(End::NeedsInput));
+                };
+                self.store(0, value)?;
+                self.pointer += 2;
+            }
+            4 => {
+                output.push(self.load(0)?);
+                self.pointer += 2;
+            }
+            5 | 6 => {
+                if (self.load(0)? != 0) == (op % 100 == 5) {
+                    self.pointer = usize::try_from(self.load(1)?).map_err(|_| ())?;
+                } else {
+                    self.pointer += 3;
+                }
+            }
+            7 | 8 => {
+                let (a, b) = (self.load(0)?, self.load(1)?);
+                let result = if op % 100 == 7 { a < b } else { a == b };
+                self.store(2, Word::from(result))?;
+                self.pointer += 4;
+            }
+            9 => {
+                self.base = fits(self.base.checked_add(self.load(0)?))?;
+                self.pointer += 2;
+            }
+            99 => return Ok(Some(End::Halted)),
+            _ => return Err(()),
+        }
+        Ok(None)
+    }
+
+    fn mode(&self, index: u32) -> Word {
+        self.memory[self.pointer] / (10 as Word).pow(index + 2) % 10
+    }
+
+    fn arg(&self, index: u32) -> Word {
+        let address = self.pointer + 1 + index as usize;
+        self.memory.get(address).copied().unwrap_or(0)
+    }
+
+    fn address(&self, index: u32) -> Result<usize, ()> {
+        let address = match self.mode(index) {
+            0 => self.arg(index),
+            2 => fits(self.base.checked_add(self.arg(index)))?,
+            _ => return Err(()),
+        };
+        usize::try_from(address).map_err(|_| ())
+    }
+
+    fn load(&self, index: u32) -> Result<Word, ()> {
+        if self.mode(index) == 1 {
+            return Ok(self.arg(index));
+        }
+        let address = self.address(index)?;
+        Ok(self.memory.get(address).copied().unwrap_or(0))
+    }
+
+    fn store(&mut self, index: u32, value: Word) -> Result<(), ()> {
+        let address = self.address(index)?;
+        if address >= MAX_ADDRESS {
+            self.too_large = true;
+            return Err(());
+        }
+        if self.memory.len() <= address {
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = value;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, fuzz, minimize, reference, Case, End};
+
+    #[test]
+    fn test_reference() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let case = Case {
+            program: quine
+                .split(',')
+                .map(|value| value.parse().unwrap())
+                .collect(),
+            input: vec![],
+        };
+        let outcome = reference(&case, 1000).unwrap();
+        assert_eq!(outcome.end, End::Halted);
+        assert_eq!(outcome.output, case.program);
+        assert!(check(&case, 1000).is_none());
+        assert_eq!(reference(&case, 10).unwrap().end, End::StepLimit);
+    }
+
+    #[test]
+    fn test_fuzz() {
+        if let Some(mismatch) = fuzz(1, 2000, 1000) {
+            panic!("{}", mismatch);
+        }
+    }
+
+    #[test]
+    fn test_minimize() {
+        let case = Case {
+            program: vec![1, 7, 3, 7, 99],
+            input: vec![4, 5],
+        };
+        let minimized = minimize(case, |case| {
+            case.program.iter().filter(|&&value| value == 7).count() >= 2
+        });
+        assert_eq!(
+            minimized,
+            Case {
+                program: vec![7, 7],
+                input: vec![]
+            }
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use super::{
+    instruction::Instruction,
+    step::{Step, Write},
+    Word,
+};
+
+/// Enough of each executed instruction to undo it.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct History(Vec<Entry>);
+
+#[derive(Clone, Debug)]
+pub(crate) struct Entry {
+    pub(crate) pointer: usize,
+    pub(crate) relative_base: Word,
+    /// The memory length before the instruction ran, since a write past the
+    /// end grows it.
+    pub(crate) memory_len: usize,
+    pub(crate) write: Option<Write>,
+    /// The value read, for `Input`.
+    pub(crate) input: Option<Word>,
+    pub(crate) output: bool,
+    /// Whether an `Output` value went to the output queue.
+    pub(crate) queued: bool,
+}
+
+impl History {
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Entry> {
+        self.0.pop()
+    }
+
+    /// How many entries to undo to reach the most recent one that is
+    /// `found`, including it.
+    pub(crate) fn count_back_to(&self, found: impl Fn(&Entry) -> bool) -> Option<usize> {
+        Some(self.0.iter().rev().position(found)? + 1)
+    }
+
+    pub(crate) fn record(&mut self, step: &Step, memory_len: usize, queued: bool) {
+        let input = match step.instruction {
+            Instruction::Input(_) => step.write.map(|write| write.new),
+            _ => None,
+        };
+        self.0.push(Entry {
+            pointer: step.pointer,
+            relative_base: step.relative_base,
+            memory_len,
+            write: step.write,
+            input,
+            output: matches!(step.instruction, Instruction::Output(_)),
+            queued,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::computer::{Computer, RunState};
+
+    #[test]
+    fn test_step_back() {
+        let mut computer = Computer::new("1,0,0,0,2,0,0,0,99".parse().unwrap());
+        assert_eq!(computer.step_back(1), 0);
+        computer.set_history(true);
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.memory()[0], 4);
+        assert_eq!(computer.history_len(), 3);
+
+        assert_eq!(computer.step_back(1), 1);
+        assert!(!computer.is_halted());
+        assert_eq!(computer.pointer(), 8);
+        assert_eq!(computer.step_back(5), 2);
+        assert_eq!(computer.pointer(), 0);
+        assert_eq!(computer.memory()[0], 1);
+
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.memory()[0], 4);
+
+        // writes past the end of memory, then outputs what it wrote
+        let mut computer = Computer::new("1101,2,3,9,4,9,99".parse().unwrap());
+        computer.set_history(true);
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.memory().len(), 10);
+        assert_eq!(computer.step_back(1), 1);
+        assert_eq!(computer.get_output(), vec![5]);
+        assert_eq!(computer.step_back(1), 1);
+        assert!(computer.get_output().is_empty());
+        assert_eq!(computer.step_back(1), 1);
+        assert_eq!(computer.memory().len(), 7);
+    }
+
+    #[test]
+    fn test_rewind() {
+        // prints the running total of its input, moving the relative base
+        let program = "3,15,1,15,16,16,4,16,109,1,1105,1,0,99,0,0,0";
+        let mut computer = Computer::new(program.parse().unwrap());
+        computer.set_history(true);
+        computer.set_input(vec![2, 3]);
+        assert_eq!(computer.run(), Ok(RunState::NeedsInput));
+        assert_eq!(computer.get_output(), vec![2, 5]);
+        assert_eq!(computer.relative_base(), 2);
+
+        assert_eq!(computer.rewind_to_output(), Some(3));
+        assert_eq!(computer.pointer(), 6);
+        assert_eq!(computer.get_output(), vec![2]);
+        assert_eq!(computer.rewind_to_input(), Some(2));
+        assert_eq!((computer.pointer(), computer.relative_base()), (0, 1));
+        assert_eq!(computer.memory()[16], 2);
+
+        assert_eq!(computer.run(), Ok(RunState::NeedsInput));
+        assert_eq!(computer.get_output(), vec![2, 5]);
+        assert_eq!(computer.step_back(usize::MAX), 10);
+        assert_eq!(computer.memory()[16], 0);
+        assert_eq!(computer.rewind_to_input(), None);
+    }
+}
@@ -1,7 +1,12 @@
-use super::Computer;
+use std::fmt;
+
+use super::{
+    error::ComputerError, instruction_set::InstructionSet, memory::Memory, Arithmetic, Computer,
+    Word,
+};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub(crate) enum Instruction {
+pub enum Instruction {
     Add(Param, Param, Param),
     Mult(Param, Param, Param),
     Input(Param),
@@ -12,41 +17,151 @@ pub(crate) enum Instruction {
     Equals(Param, Param, Param),
     RelativeBase(Param),
     Stop,
+    /// An opcode registered in an `InstructionSet`.
+    Custom {
+        code: usize,
+        mnemonic: &'static str,
+        params: Vec<Param>,
+        /// Index of the parameter written to, if any.
+        write: Option<usize>,
+    },
 }
 
 impl Instruction {
-    pub fn from_opcode(
+    pub fn decode(memory: &Memory, pointer: usize) -> Result<Instruction, ComputerError> {
+        Self::decode_with(memory, pointer, None)
+    }
+
+    /// Decodes with the custom opcodes in `set` as well as the standard ones.
+    pub fn decode_with(
+        memory: &Memory,
+        pointer: usize,
+        set: Option<&InstructionSet>,
+    ) -> Result<Instruction, ComputerError> {
+        let Some(&value) = memory.get(pointer) else {
+            return Err(ComputerError::PointerOutOfBounds { pointer });
+        };
+        let Ok(value_usize) = usize::try_from(value) else {
+            return Err(ComputerError::InvalidOpcode {
+                opcode: value,
+                pointer,
+            });
+        };
+
+        let opcode: Opcode = value_usize.into();
+        if let Some(&mode) = opcode.param_modes.iter().find(|&&mode| mode > 2) {
+            return Err(ComputerError::UnknownParamMode { mode, pointer });
+        }
+        if let Some(op) = set.and_then(|set| set.get(opcode.code)) {
+            let mut modes = opcode.param_modes.iter();
+            let mut cells = memory.iter_from(pointer + 1);
+            let params = (0..op.arity)
+                .map(|_| Self::get_param(modes.next(), cells.next()))
+                .collect::<Option<_>>()
+                .ok_or(ComputerError::InvalidOpcode {
+                    opcode: value,
+                    pointer,
+                })?;
+            return Ok(Self::Custom {
+                code: op.code,
+                mnemonic: op.mnemonic,
+                params,
+                write: op.write,
+            });
+        }
+
+        Self::from_opcode(opcode, memory.iter_from(pointer)).ok_or(ComputerError::InvalidOpcode {
+            opcode: value,
+            pointer,
+        })
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Add(..) => "ADD",
+            Self::Mult(..) => "MUL",
+            Self::Input(..) => "IN",
+            Self::Output(..) => "OUT",
+            Self::JumpIfTrue(..) => "JT",
+            Self::JumpIfFalse(..) => "JF",
+            Self::LessThan(..) => "LT",
+            Self::Equals(..) => "EQ",
+            Self::RelativeBase(..) => "ARB",
+            Self::Stop => "HLT",
+            Self::Custom { mnemonic, .. } => mnemonic,
+        }
+    }
+
+    pub fn params(&self) -> Vec<&Param> {
+        match self {
+            Self::Add(p1, p2, p3)
+            | Self::Mult(p1, p2, p3)
+            | Self::LessThan(p1, p2, p3)
+            | Self::Equals(p1, p2, p3) => vec![p1, p2, p3],
+            Self::JumpIfTrue(p1, p2) | Self::JumpIfFalse(p1, p2) => vec![p1, p2],
+            Self::Input(p1) | Self::Output(p1) | Self::RelativeBase(p1) => vec![p1],
+            Self::Stop => vec![],
+            Self::Custom { params, .. } => params.iter().collect(),
+        }
+    }
+
+    /// Index of the parameter the instruction writes to, if any.
+    pub fn write(&self) -> Option<usize> {
+        match self {
+            Self::Add(..) | Self::Mult(..) | Self::LessThan(..) | Self::Equals(..) => Some(2),
+            Self::Input(..) => Some(0),
+            Self::Output(..)
+            | Self::JumpIfTrue(..)
+            | Self::JumpIfFalse(..)
+            | Self::RelativeBase(..)
+            | Self::Stop => None,
+            Self::Custom { write, .. } => *write,
+        }
+    }
+
+    /// Number of memory cells the instruction occupies, opcode included.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Add(..) | Self::Mult(..) | Self::LessThan(..) | Self::Equals(..) => 4,
+            Self::JumpIfTrue(..) | Self::JumpIfFalse(..) => 3,
+            Self::Input(..) | Self::Output(..) | Self::RelativeBase(..) => 2,
+            Self::Stop => 1,
+            Self::Custom { params, .. } => 1 + params.len(),
+        }
+    }
+
+    pub fn from_opcode<'a>(
         opcode: Opcode,
-        mut mem: std::slice::Iter<'_, isize>,
+        mut mem: impl Iterator<Item = &'a Word>,
     ) -> Option<Instruction> {
         // consume the opcode
-        mem.next().unwrap();
+        mem.next()?;
         if opcode.code == 1 {
-            let (p1, p2, p3) = Self::get_params3(&opcode.param_modes, mem);
+            let (p1, p2, p3) = Self::get_params3(&opcode.param_modes, mem)?;
             Some(Self::Add(p1, p2, p3))
         } else if opcode.code == 2 {
-            let (p1, p2, p3) = Self::get_params3(&opcode.param_modes, mem);
+            let (p1, p2, p3) = Self::get_params3(&opcode.param_modes, mem)?;
             Some(Self::Mult(p1, p2, p3))
         } else if opcode.code == 3 {
-            let p1 = Self::get_params1(&opcode.param_modes, mem);
+            let p1 = Self::get_params1(&opcode.param_modes, mem)?;
             Some(Self::Input(p1))
         } else if opcode.code == 4 {
-            let p1 = Self::get_params1(&opcode.param_modes, mem);
+            let p1 = Self::get_params1(&opcode.param_modes, mem)?;
             Some(Self::Output(p1))
         } else if opcode.code == 5 {
-            let (p1, p2) = Self::get_params2(&opcode.param_modes, mem);
+            let (p1, p2) = Self::get_params2(&opcode.param_modes, mem)?;
             Some(Self::JumpIfTrue(p1, p2))
         } else if opcode.code == 6 {
-            let (p1, p2) = Self::get_params2(&opcode.param_modes, mem);
+            let (p1, p2) = Self::get_params2(&opcode.param_modes, mem)?;
             Some(Self::JumpIfFalse(p1, p2))
         } else if opcode.code == 7 {
-            let (p1, p2, p3) = Self::get_params3(&opcode.param_modes, mem);
+            let (p1, p2, p3) = Self::get_params3(&opcode.param_modes, mem)?;
             Some(Self::LessThan(p1, p2, p3))
         } else if opcode.code == 8 {
-            let (p1, p2, p3) = Self::get_params3(&opcode.param_modes, mem);
+            let (p1, p2, p3) = Self::get_params3(&opcode.param_modes, mem)?;
             Some(Self::Equals(p1, p2, p3))
         } else if opcode.code == 9 {
-            let p1 = Self::get_params1(&opcode.param_modes, mem);
+            let p1 = Self::get_params1(&opcode.param_modes, mem)?;
             Some(Self::RelativeBase(p1))
         } else if opcode.code == 99 {
             Some(Self::Stop)
@@ -55,40 +170,57 @@ impl Instruction {
         }
     }
 
-    fn get_params1(modes: &[usize], mut mem: std::slice::Iter<'_, isize>) -> Param {
+    fn get_params1<'a>(modes: &[usize], mut mem: impl Iterator<Item = &'a Word>) -> Option<Param> {
         let mut modes = modes.iter();
         Self::get_param(modes.next(), mem.next())
     }
 
-    fn get_params2(modes: &[usize], mut mem: std::slice::Iter<'_, isize>) -> (Param, Param) {
+    fn get_params2<'a>(
+        modes: &[usize],
+        mut mem: impl Iterator<Item = &'a Word>,
+    ) -> Option<(Param, Param)> {
         let mut modes = modes.iter();
-        let p1 = Self::get_param(modes.next(), mem.next());
-        let p2 = Self::get_param(modes.next(), mem.next());
-        (p1, p2)
+        let p1 = Self::get_param(modes.next(), mem.next())?;
+        let p2 = Self::get_param(modes.next(), mem.next())?;
+        Some((p1, p2))
     }
 
-    fn get_params3(modes: &[usize], mut mem: std::slice::Iter<'_, isize>) -> (Param, Param, Param) {
+    fn get_params3<'a>(
+        modes: &[usize],
+        mut mem: impl Iterator<Item = &'a Word>,
+    ) -> Option<(Param, Param, Param)> {
         let mut modes = modes.iter();
-        let p1 = Self::get_param(modes.next(), mem.next());
-        let p2 = Self::get_param(modes.next(), mem.next());
-        let p3 = Self::get_param(modes.next(), mem.next());
-        (p1, p2, p3)
-    }
-
-    fn get_param(mode: Option<&usize>, mem: Option<&isize>) -> Param {
-        let mode = *mode.unwrap_or(&0);
-        if mode == 0 {
-            Param::Pos(*mem.unwrap() as usize)
-        } else if mode == 1 {
-            Param::Imm(*mem.unwrap())
-        } else {
-            Param::Rel(*mem.unwrap())
+        let p1 = Self::get_param(modes.next(), mem.next())?;
+        let p2 = Self::get_param(modes.next(), mem.next())?;
+        let p3 = Self::get_param(modes.next(), mem.next())?;
+        Some((p1, p2, p3))
+    }
+
+    fn get_param(mode: Option<&usize>, mem: Option<&Word>) -> Option<Param> {
+        // cells past the end of the program read as 0, same as `Param::value`
+        let value = *mem.unwrap_or(&0);
+        match *mode.unwrap_or(&0) {
+            0 => Some(Param::Pos(value)),
+            1 => Some(Param::Imm(value)),
+            2 => Some(Param::Rel(value)),
+            _ => None,
         }
     }
 }
 
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.mnemonic())?;
+        for (index, param) in self.params().iter().enumerate() {
+            let separator = if index == 0 { " " } else { ", " };
+            write!(f, "{}{}", separator, param)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub(crate) struct Opcode {
+pub struct Opcode {
     code: usize,
     param_modes: Vec<usize>,
 }
@@ -105,9 +237,7 @@ impl From<usize> for Opcode {
         }
 
         let mut digits = digits.into_iter();
-        let Some(ones_place) = digits.next() else {
-            panic!("No ones place in op code")
-        };
+        let ones_place = digits.next().unwrap_or(0);
         let tens_place = digits.next().unwrap_or(0);
         let code = tens_place * 10 + ones_place;
 
@@ -119,29 +249,70 @@ impl From<usize> for Opcode {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub(crate) enum Param {
-    Pos(usize),
-    Imm(isize),
-    Rel(isize),
+pub enum Param {
+    Pos(Word),
+    Imm(Word),
+    Rel(Word),
 }
 
 impl Param {
-    pub fn value(&self, computer: &Computer) -> isize {
+    pub fn value(&self, computer: &Computer) -> Result<Word, ComputerError> {
         match self {
-            Self::Pos(idx) => *computer.memory.get(*idx).unwrap_or(&0),
-            Self::Imm(num) => *num,
-            Self::Rel(idx) => {
-                let idx = computer.relative_base + idx;
-                *computer.memory.get(idx as usize).unwrap_or(&0)
+            Self::Imm(num) => Ok(*num),
+            _ => {
+                let idx = self.as_pos(computer)?;
+                Ok(*computer.memory.get(idx).unwrap_or(&0))
             }
         }
     }
 
-    pub fn as_pos(&self, computer: &Computer) -> usize {
-        match self {
+    pub fn as_pos(&self, computer: &Computer) -> Result<usize, ComputerError> {
+        self.address(
+            computer.relative_base,
+            computer.arithmetic,
+            computer.pointer,
+        )
+    }
+
+    /// The address a position or relative parameter refers to, for an
+    /// instruction at `pointer`. Relative addresses are summed with
+    /// `arithmetic`.
+    pub(crate) fn address(
+        &self,
+        relative_base: Word,
+        arithmetic: Arithmetic,
+        pointer: usize,
+    ) -> Result<usize, ComputerError> {
+        let address = match self {
             Self::Pos(idx) => *idx,
-            Self::Imm(num) => *num as usize,
-            Self::Rel(idx) => (computer.relative_base + idx) as usize,
+            Self::Imm(_) => return Err(ComputerError::WriteToImmediate { pointer }),
+            Self::Rel(idx) => arithmetic
+                .add(relative_base, *idx)
+                .ok_or(ComputerError::Overflow { pointer })?,
+        };
+        to_address(address, pointer)
+    }
+}
+
+/// Converts a value used as an address by the instruction at `pointer`.
+/// `usize::MAX` is out of range too, since memory reaching it would have a
+/// length that doesn't fit in a `usize`.
+pub(crate) fn to_address(address: Word, pointer: usize) -> Result<usize, ComputerError> {
+    if address < 0 {
+        return Err(ComputerError::NegativeAddress { address, pointer });
+    }
+    match usize::try_from(address) {
+        Ok(address) if address < usize::MAX => Ok(address),
+        _ => Err(ComputerError::AddressOutOfRange { address, pointer }),
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pos(address) => write!(f, "[pos {}]", address),
+            Self::Imm(num) => write!(f, "#{}", num),
+            Self::Rel(offset) => write!(f, "[rel {:+}]", offset),
         }
     }
 }
@@ -150,7 +321,7 @@ impl Param {
 mod tests {
     use crate::computer::instruction::Param;
 
-    use super::{Instruction, Opcode};
+    use super::{Instruction, Opcode, Word};
 
     #[test]
     fn test_opcode_generation() {
@@ -184,11 +355,22 @@ mod tests {
     fn test_opcode_to_instruction() {
         let input = 11002;
         let opcode: Opcode = input.into();
-        let mem: Vec<isize> = vec![0, 1, 2, 3, 4, 5];
+        let mem: Vec<Word> = vec![0, 1, 2, 3, 4, 5];
         let inst = Instruction::from_opcode(opcode, mem.iter()).unwrap();
         assert_eq!(
             inst,
             Instruction::Mult(Param::Pos(1), Param::Imm(2), Param::Imm(3))
         );
     }
+
+    #[test]
+    fn test_instruction_display() {
+        let inst = Instruction::Add(Param::Pos(9), Param::Imm(5), Param::Rel(-1));
+        assert_eq!(inst.to_string(), "ADD [pos 9], #5, [rel -1]");
+        assert_eq!(
+            Instruction::Output(Param::Rel(3)).to_string(),
+            "OUT [rel +3]"
+        );
+        assert_eq!(Instruction::Stop.to_string(), "HLT");
+    }
 }
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use super::{error::ComputerError, instruction::Instruction, memory::Memory, Word};
+
+type Execute = dyn Fn(&[Word]) -> Option<Word> + Send + Sync;
+
+/// An opcode added on top of the standard ones.
+#[derive(Clone)]
+pub struct CustomOp {
+    pub code: usize,
+    pub mnemonic: &'static str,
+    pub arity: usize,
+    /// Index of the parameter the result is written to, if any.
+    pub write: Option<usize>,
+    execute: Arc<Execute>,
+}
+
+impl CustomOp {
+    /// `execute` gets the values of the read parameters, in order, and
+    /// returns the value to write. Returning `None` skips the write.
+    pub fn new<F>(
+        code: usize,
+        mnemonic: &'static str,
+        arity: usize,
+        write: Option<usize>,
+        execute: F,
+    ) -> Self
+    where
+        F: Fn(&[Word]) -> Option<Word> + Send + Sync + 'static,
+    {
+        Self {
+            code,
+            mnemonic,
+            arity,
+            write,
+            execute: Arc::new(execute),
+        }
+    }
+
+    pub(crate) fn execute(&self, operands: &[Word]) -> Option<Word> {
+        (self.execute)(operands)
+    }
+}
+
+impl fmt::Debug for CustomOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomOp")
+            .field("code", &self.code)
+            .field("mnemonic", &self.mnemonic)
+            .field("arity", &self.arity)
+            .field("write", &self.write)
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InstructionSetError {
+    /// The code belongs to a standard instruction, or is registered already.
+    Taken(usize),
+    /// Codes are the last two digits of an opcode.
+    InvalidCode(usize),
+    WriteOutOfRange {
+        code: usize,
+        write: usize,
+    },
+}
+
+impl fmt::Display for InstructionSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Taken(code) => write!(f, "opcode {} is already in use", code),
+            Self::InvalidCode(code) => write!(f, "opcode {} is not between 1 and 99", code),
+            Self::WriteOutOfRange { code, write } => write!(
+                f,
+                "opcode {} writes to parameter {} it doesn't have",
+                code, write
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InstructionSetError {}
+
+/// The standard instructions plus registered custom ones, decoded as
+/// `Instruction::Custom`. Attach one to a machine with
+/// `Machine::set_instruction_set`, or pass it to `assemble_with`,
+/// `disassemble_with` and `control_flow_with` to build, list and graph
+/// programs that use it. Custom ops run Rust closures, so only library code
+/// can define them; the command-line tools know the standard set only.
+#[derive(Clone, Debug, Default)]
+pub struct InstructionSet {
+    custom: HashMap<usize, CustomOp>,
+}
+
+impl InstructionSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, op: CustomOp) -> Result<&mut Self, InstructionSetError> {
+        let code = op.code;
+        if !(1..100).contains(&code) {
+            return Err(InstructionSetError::InvalidCode(code));
+        }
+        if (1..=9).contains(&code) || code == 99 || self.custom.contains_key(&code) {
+            return Err(InstructionSetError::Taken(code));
+        }
+        if let Some(write) = op.write.filter(|&write| write >= op.arity) {
+            return Err(InstructionSetError::WriteOutOfRange { code, write });
+        }
+        self.custom.insert(code, op);
+        Ok(self)
+    }
+
+    pub fn get(&self, code: usize) -> Option<&CustomOp> {
+        self.custom.get(&code)
+    }
+
+    pub fn decode(&self, memory: &Memory, pointer: usize) -> Result<Instruction, ComputerError> {
+        Instruction::decode_with(memory, pointer, Some(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{CustomOp, InstructionSet, InstructionSetError};
+    use crate::computer::{
+        instruction::{Instruction, Param},
+        machine::Backend,
+        RunState,
+    };
+
+    fn and() -> CustomOp {
+        CustomOp::new(20, "AND", 3, Some(2), |values| Some(values[0] & values[1]))
+    }
+
+    #[test]
+    fn test_register() {
+        let mut set = InstructionSet::new();
+        assert!(set.register(and()).is_ok());
+        assert_eq!(
+            set.register(and()).unwrap_err(),
+            InstructionSetError::Taken(20)
+        );
+
+        let errors = [
+            (
+                CustomOp::new(7, "X", 0, None, |_| None),
+                InstructionSetError::Taken(7),
+            ),
+            (
+                CustomOp::new(100, "X", 0, None, |_| None),
+                InstructionSetError::InvalidCode(100),
+            ),
+            (
+                CustomOp::new(21, "X", 1, Some(1), |_| None),
+                InstructionSetError::WriteOutOfRange { code: 21, write: 1 },
+            ),
+        ];
+        for (op, error) in errors {
+            assert_eq!(set.register(op).unwrap_err(), error);
+        }
+
+        let memory = "1120,12,10,5,99".parse().unwrap();
+        let instruction = set.decode(&memory, 0).unwrap();
+        assert_eq!(instruction.to_string(), "AND #12, #10, [pos 5]");
+        assert_eq!(instruction.size(), 4);
+        assert_eq!(
+            instruction.params(),
+            vec![&Param::Imm(12), &Param::Imm(10), &Param::Pos(5)]
+        );
+        assert!(Instruction::decode(&memory, 0).is_err());
+    }
+
+    #[test]
+    fn test_custom_ops() {
+        let printed = Arc::new(Mutex::new(vec![]));
+        let log = Arc::clone(&printed);
+        let mut set = InstructionSet::new();
+        set.register(and())
+            .unwrap()
+            .register(CustomOp::new(30, "DBG", 1, None, move |values| {
+                log.lock().unwrap().push(values[0]);
+                None
+            }))
+            .unwrap();
+
+        let mut sum = InstructionSet::new();
+        sum.register(CustomOp::new(40, "SUM", 4, Some(3), |values| {
+            Some(values.iter().sum())
+        }))
+        .unwrap();
+
+        for backend in Backend::ALL {
+            // ANDs the input with 12, then prints and outputs the result
+            let program = "3,13,1020,13,12,13,30,13,4,13,99,0,0,0";
+            let mut computer = backend.machine(program.parse().unwrap());
+            computer.set_instruction_set(set.clone());
+            computer.push_input(10);
+            assert_eq!(computer.run(), Ok(RunState::Halted));
+            assert_eq!(computer.get_output(), vec![8]);
+            assert_eq!(
+                printed.lock().unwrap().drain(..).collect::<Vec<_>>(),
+                vec![8]
+            );
+
+            // runs a five-cell SUM twice, patching its write target in between
+            let program = "11140,1,2,3,20,1005,21,19,1101,0,1,21,1101,0,22,4,1105,1,0,99,0,0,0";
+            let mut computer = backend.machine(program.parse().unwrap());
+            computer.set_instruction_set(sum.clone());
+            assert_eq!(computer.run(), Ok(RunState::Halted));
+            assert_eq!(computer.memory()[20], 6);
+            assert_eq!(computer.memory()[22], 6);
+        }
+    }
+}
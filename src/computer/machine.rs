@@ -0,0 +1,161 @@
+use super::{
+    compiled::CompiledComputer,
+    device::{InputDevice, OutputDevice},
+    error::ComputerError,
+    instruction_set::InstructionSet,
+    memory::Memory,
+    Computer, RunState, Word,
+};
+
+/// What puzzle code needs from a machine, whichever backend runs it.
+pub trait Machine {
+    fn set_yield_on_output(&mut self, val: bool);
+    fn set_input(&mut self, input: Vec<Word>);
+    fn push_input(&mut self, input: Word);
+    fn get_output(&self) -> Vec<Word>;
+    fn next_output(&mut self) -> Option<Word>;
+    fn set_input_device(&mut self, device: Box<dyn InputDevice + Send>);
+    fn set_output_device(&mut self, device: Box<dyn OutputDevice + Send>);
+    fn clear_input_device(&mut self);
+    fn clear_output_device(&mut self);
+    fn is_halted(&self) -> bool;
+    fn memory(&self) -> &Memory;
+    fn set_memory(&mut self, address: usize, value: Word);
+    fn set_instruction_set(&mut self, set: InstructionSet);
+    fn run(&mut self) -> Result<RunState, ComputerError>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// `Computer`, which decodes as it goes.
+    Interpreter,
+    /// `CompiledComputer`, which compiles the image up front.
+    Compiled,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 2] = [Self::Interpreter, Self::Compiled];
+
+    pub fn machine(self, memory: Memory) -> Box<dyn Machine + Send> {
+        match self {
+            Self::Interpreter => Box::new(Computer::new(memory)),
+            Self::Compiled => Box::new(CompiledComputer::new(memory)),
+        }
+    }
+}
+
+impl<M: Machine + ?Sized> Machine for Box<M> {
+    fn set_yield_on_output(&mut self, val: bool) {
+        (**self).set_yield_on_output(val)
+    }
+
+    fn set_input(&mut self, input: Vec<Word>) {
+        (**self).set_input(input)
+    }
+
+    fn push_input(&mut self, input: Word) {
+        (**self).push_input(input)
+    }
+
+    fn get_output(&self) -> Vec<Word> {
+        (**self).get_output()
+    }
+
+    fn next_output(&mut self) -> Option<Word> {
+        (**self).next_output()
+    }
+
+    fn set_input_device(&mut self, device: Box<dyn InputDevice + Send>) {
+        (**self).set_input_device(device)
+    }
+
+    fn set_output_device(&mut self, device: Box<dyn OutputDevice + Send>) {
+        (**self).set_output_device(device)
+    }
+
+    fn clear_input_device(&mut self) {
+        (**self).clear_input_device()
+    }
+
+    fn clear_output_device(&mut self) {
+        (**self).clear_output_device()
+    }
+
+    fn is_halted(&self) -> bool {
+        (**self).is_halted()
+    }
+
+    fn memory(&self) -> &Memory {
+        (**self).memory()
+    }
+
+    fn set_memory(&mut self, address: usize, value: Word) {
+        (**self).set_memory(address, value)
+    }
+
+    fn set_instruction_set(&mut self, set: InstructionSet) {
+        (**self).set_instruction_set(set)
+    }
+
+    fn run(&mut self) -> Result<RunState, ComputerError> {
+        (**self).run()
+    }
+}
+
+impl Machine for Computer {
+    fn set_yield_on_output(&mut self, val: bool) {
+        Computer::set_yield_on_output(self, val)
+    }
+
+    fn set_input(&mut self, input: Vec<Word>) {
+        Computer::set_input(self, input)
+    }
+
+    fn push_input(&mut self, input: Word) {
+        Computer::push_input(self, input)
+    }
+
+    fn get_output(&self) -> Vec<Word> {
+        Computer::get_output(self)
+    }
+
+    fn next_output(&mut self) -> Option<Word> {
+        Computer::next_output(self)
+    }
+
+    fn set_input_device(&mut self, device: Box<dyn InputDevice + Send>) {
+        Computer::set_input_device(self, device)
+    }
+
+    fn set_output_device(&mut self, device: Box<dyn OutputDevice + Send>) {
+        Computer::set_output_device(self, device)
+    }
+
+    fn clear_input_device(&mut self) {
+        Computer::clear_input_device(self)
+    }
+
+    fn clear_output_device(&mut self) {
+        Computer::clear_output_device(self)
+    }
+
+    fn is_halted(&self) -> bool {
+        Computer::is_halted(self)
+    }
+
+    fn memory(&self) -> &Memory {
+        Computer::memory(self)
+    }
+
+    fn set_memory(&mut self, address: usize, value: Word) {
+        Computer::set_memory(self, address, value)
+    }
+
+    fn set_instruction_set(&mut self, set: InstructionSet) {
+        Computer::set_instruction_set(self, set)
+    }
+
+    fn run(&mut self) -> Result<RunState, ComputerError> {
+        Computer::run(self)
+    }
+}
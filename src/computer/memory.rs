@@ -1,31 +1,328 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    collections::HashMap,
+    fmt,
+    num::ParseIntError,
+    ops::{Index, IndexMut},
+    str::FromStr,
+};
+
+use super::Word;
+
+pub const PAGE_SIZE: usize = 4096;
+
+static ZERO: Word = 0;
+
+/// A machine's memory. The dense backend is a single `Vec`; the sparse
+/// backend allocates `PAGE_SIZE` pages on first write, so programs can use
+/// huge addresses without allocating everything below them. Cells that were
+/// never written read as 0 in both.
+#[derive(Clone, Debug)]
+pub struct Memory(Backend);
 
 #[derive(Clone, Debug)]
-pub(crate) struct Memory(Vec<isize>);
+enum Backend {
+    Dense(Vec<Word>),
+    Sparse(Pages),
+}
+
+#[derive(Clone, Debug, Default)]
+struct Pages {
+    pages: HashMap<usize, Box<[Word; PAGE_SIZE]>>,
+    len: usize,
+}
+
+impl Pages {
+    fn get(&self, address: usize) -> &Word {
+        match self.pages.get(&(address / PAGE_SIZE)) {
+            Some(page) => &page[address % PAGE_SIZE],
+            None => &ZERO,
+        }
+    }
+
+    fn get_mut(&mut self, address: usize) -> &mut Word {
+        let page = self
+            .pages
+            .entry(address / PAGE_SIZE)
+            .or_insert_with(|| Box::new([0; PAGE_SIZE]));
+        &mut page[address % PAGE_SIZE]
+    }
+}
 
 impl Memory {
-    pub fn new(data: Vec<isize>) -> Self {
-        Self(data)
+    pub fn new(data: Vec<Word>) -> Self {
+        Self(Backend::Dense(data))
+    }
+
+    pub fn sparse(data: Vec<Word>) -> Self {
+        Self::new(data).into_sparse()
+    }
+
+    pub fn into_sparse(self) -> Self {
+        match self.0 {
+            Backend::Dense(data) => {
+                let mut pages = Pages {
+                    len: data.len(),
+                    ..Default::default()
+                };
+                for (address, value) in data.into_iter().enumerate() {
+                    if value != 0 {
+                        *pages.get_mut(address) = value;
+                    }
+                }
+                Self(Backend::Sparse(pages))
+            }
+            Backend::Sparse(_) => self,
+        }
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        matches!(self.0, Backend::Sparse(_))
+    }
+
+    /// One past the highest address that was loaded or written.
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            Backend::Dense(data) => data.len(),
+            Backend::Sparse(pages) => pages.len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, address: usize) -> Option<&Word> {
+        match &self.0 {
+            Backend::Dense(data) => data.get(address),
+            Backend::Sparse(pages) if address < pages.len => Some(pages.get(address)),
+            Backend::Sparse(_) => None,
+        }
+    }
+
+    pub fn first(&self) -> Option<&Word> {
+        self.get(0)
+    }
+
+    /// Writes `value`, growing the memory if `address` is past the end.
+    ///
+    /// Panics if `address` is `usize::MAX`, since the length would overflow.
+    pub fn set(&mut self, address: usize, value: Word) {
+        let end = address
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("address {} is out of range", address));
+        match &mut self.0 {
+            Backend::Dense(data) => {
+                if data.len() < end {
+                    data.resize(end, 0);
+                }
+                data[address] = value;
+            }
+            Backend::Sparse(pages) => {
+                pages.len = pages.len.max(end);
+                if value != 0 || pages.pages.contains_key(&(address / PAGE_SIZE)) {
+                    *pages.get_mut(address) = value;
+                }
+            }
+        }
+    }
+
+    /// Shrinks the memory to `len` cells. Does nothing if it is already
+    /// shorter.
+    pub fn truncate(&mut self, len: usize) {
+        match &mut self.0 {
+            Backend::Dense(data) => data.truncate(len),
+            Backend::Sparse(pages) if len < pages.len => {
+                pages.len = len;
+                pages.pages.retain(|&number, _| number * PAGE_SIZE < len);
+                if let Some(page) = pages.pages.get_mut(&(len / PAGE_SIZE)) {
+                    page[len % PAGE_SIZE..].fill(0);
+                }
+            }
+            Backend::Sparse(_) => {}
+        }
+    }
+
+    pub fn iter_from(&self, address: usize) -> impl Iterator<Item = &Word> + '_ {
+        (address..self.len()).map(|address| &self[address])
+    }
+
+    /// The first address at or after `address` that may be non-zero, or
+    /// `len` if there is none. Always `address` for the dense backend; the
+    /// sparse backend skips pages that were never written.
+    pub fn next_allocated(&self, address: usize) -> usize {
+        match &self.0 {
+            Backend::Dense(_) => address.min(self.len()),
+            Backend::Sparse(pages) if pages.pages.contains_key(&(address / PAGE_SIZE)) => {
+                address.min(pages.len)
+            }
+            Backend::Sparse(pages) => pages
+                .pages
+                .keys()
+                .filter(|&&number| number > address / PAGE_SIZE)
+                .min()
+                .map_or(pages.len, |number| (number * PAGE_SIZE).min(pages.len)),
+        }
+    }
+
+    /// Addresses and values of every cell that may be non-zero, in address
+    /// order. For the sparse backend this only visits allocated pages.
+    pub fn cells(&self) -> Vec<(usize, Word)> {
+        match &self.0 {
+            Backend::Dense(data) => data.iter().copied().enumerate().collect(),
+            Backend::Sparse(pages) => {
+                let mut numbers: Vec<usize> = pages.pages.keys().copied().collect();
+                numbers.sort_unstable();
+                numbers
+                    .into_iter()
+                    .flat_map(|number| {
+                        let start = number * PAGE_SIZE;
+                        (start..(start + PAGE_SIZE).min(pages.len))
+                            .map(|address| (address, *pages.get(address)))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Every cell up to `len`, so this allocates that much even for the
+    /// sparse backend.
+    pub fn to_vec(&self) -> Vec<Word> {
+        match &self.0 {
+            Backend::Dense(data) => data.clone(),
+            Backend::Sparse(pages) => {
+                let mut data = vec![0; pages.len];
+                for (address, value) in self.cells() {
+                    data[address] = value;
+                }
+                data
+            }
+        }
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = Word;
+
+    fn index(&self, address: usize) -> &Self::Output {
+        match self.get(address) {
+            Some(value) => value,
+            None => panic!(
+                "address {} is out of bounds for memory of length {}",
+                address,
+                self.len()
+            ),
+        }
+    }
+}
+
+impl IndexMut<usize> for Memory {
+    fn index_mut(&mut self, address: usize) -> &mut Self::Output {
+        let len = self.len();
+        match &mut self.0 {
+            Backend::Dense(data) => &mut data[address],
+            Backend::Sparse(pages) if address < len => pages.get_mut(address),
+            Backend::Sparse(_) => panic!(
+                "address {} is out of bounds for memory of length {}",
+                address, len
+            ),
+        }
     }
 }
 
 impl From<String> for Memory {
     fn from(s: String) -> Self {
-        let data: Vec<isize> = s.split(",").map(|s| s.parse::<isize>().unwrap()).collect();
+        let data: Vec<Word> = s.split(",").map(|s| s.parse::<Word>().unwrap()).collect();
         Self::new(data)
     }
 }
 
-impl Deref for Memory {
-    type Target = Vec<isize>;
+impl FromStr for Memory {
+    type Err = ParseIntError;
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let data = s
+            .trim()
+            .split(',')
+            .map(|s| s.trim().parse::<Word>())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(data))
     }
 }
 
-impl DerefMut for Memory {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+/// Dense memory displays as a comma-separated program. Sparse memory lists
+/// its non-zero cells as `address=value`, since the zeros in between could
+/// run to billions of cells.
+impl fmt::Display for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sparse = self.is_sparse();
+        let cells = self.cells().into_iter();
+        let cells = cells.filter(|(_, value)| !sparse || *value != 0);
+        for (index, (address, value)) in cells.enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            if sparse {
+                write!(f, "{}=", address)?;
+            }
+            write!(f, "{}", value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Memory, Word, PAGE_SIZE};
+
+    fn backends(data: Vec<Word>) -> [Memory; 2] {
+        [Memory::new(data.clone()), Memory::sparse(data)]
+    }
+
+    #[test]
+    fn test_read_write() {
+        for mut memory in backends(vec![1, 0, 3]) {
+            assert_eq!(memory.len(), 3);
+            assert_eq!(memory.get(0), Some(&1));
+            assert_eq!(memory.get(1), Some(&0));
+            assert_eq!(memory.get(3), None);
+
+            memory[1] = 7;
+            memory.set(5, 9);
+            assert_eq!(memory.len(), 6);
+            assert_eq!(memory.to_vec(), vec![1, 7, 3, 0, 0, 9]);
+            assert_eq!(memory.iter_from(4).copied().collect::<Vec<_>>(), vec![0, 9]);
+
+            memory.truncate(2);
+            assert_eq!(memory.len(), 2);
+            memory.set(3, 0);
+            assert_eq!(memory.to_vec(), vec![1, 7, 0, 0]);
+        }
+        assert_eq!(Memory::new(vec![1, 0, 3]).to_string(), "1,0,3");
+        assert_eq!(Memory::sparse(vec![1, 0, 3]).to_string(), "0=1,2=3");
+    }
+
+    #[test]
+    fn test_sparse_pages() {
+        let mut memory = Memory::sparse(vec![1, 2]);
+        memory.set(1_000_000_000, 5);
+        assert_eq!(memory.len(), 1_000_000_001);
+        assert_eq!(memory[1_000_000_000], 5);
+        assert_eq!(memory[PAGE_SIZE * 3], 0);
+        // the first page, and the last page up to the end of memory
+        let cells = memory.cells();
+        assert_eq!(cells.len(), PAGE_SIZE + 1_000_000_000 % PAGE_SIZE + 1);
+        assert_eq!(cells.last(), Some(&(1_000_000_000, 5)));
+        assert_eq!(memory.next_allocated(2), 2);
+        assert_eq!(
+            memory.next_allocated(PAGE_SIZE),
+            1_000_000_000 / PAGE_SIZE * PAGE_SIZE
+        );
+        assert_eq!(memory.next_allocated(1_000_000_001), 1_000_000_001);
+        assert_eq!(memory.to_string(), "0=1,1=2,1000000000=5");
+
+        memory.truncate(1);
+        assert_eq!(memory.to_string(), "0=1");
+        memory.set(PAGE_SIZE, 0);
+        assert_eq!(memory[1], 0);
     }
 }
@@ -1,21 +1,148 @@
+pub mod ascii;
+pub mod assembler;
+mod cache;
+pub mod cfg;
+pub mod compiled;
+pub mod device;
+pub mod disassembler;
+pub mod error;
+#[cfg(any(test, feature = "fuzz"))]
+pub mod fuzz;
+mod history;
 pub mod instruction;
+pub mod instruction_set;
+pub mod machine;
 pub mod memory;
+pub mod network;
+pub mod profile;
+pub mod snapshot;
+pub mod step;
+pub mod symbolic;
+pub mod threaded;
+pub mod topology;
+pub mod trace;
+pub mod watch;
 
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
-use instruction::{Instruction, Opcode};
+use ascii::{Ascii, NotAscii};
+use cache::DecodeCache;
+use device::{Device, InputDevice, OutputDevice};
+use error::ComputerError;
+use history::History;
+use instruction::{Instruction, Param};
+use instruction_set::InstructionSet;
 use memory::Memory;
+use profile::Profile;
+use step::{Step, Write};
+use trace::{Fault, Trace, TraceSink};
+use watch::{Hit, Watches};
+
+/// Dense memory grows to at most this many cells by default. Sparse memory
+/// only allocates the pages that are written, so it has no limit.
+pub const DEFAULT_MAX_DENSE_LEN: usize = 1 << 24;
+
+/// A memory cell. Cells are 128 bits wide so that any machine can run with
+/// `Arithmetic::Wide`; the other policies keep results within 64 bits.
+pub type Word = i128;
+
+/// What `Add`, `Mult` and relative base updates do when a result does not
+/// fit, and how wide that is. Relative addresses are computed the same way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Arithmetic {
+    /// 64-bit results; anything wider stops with `ComputerError::Overflow`.
+    #[default]
+    Checked,
+    /// 64-bit results that wrap around.
+    Wrapping,
+    /// 128-bit results; anything wider stops with `ComputerError::Overflow`.
+    Wide,
+}
+
+impl Arithmetic {
+    /// `None` on overflow.
+    pub fn add(self, a: Word, b: Word) -> Option<Word> {
+        self.apply(a, b, Word::checked_add, i64::wrapping_add)
+    }
+
+    /// `None` on overflow.
+    pub fn mul(self, a: Word, b: Word) -> Option<Word> {
+        self.apply(a, b, Word::checked_mul, i64::wrapping_mul)
+    }
+
+    fn apply(
+        self,
+        a: Word,
+        b: Word,
+        checked: fn(Word, Word) -> Option<Word>,
+        wrapping: fn(i64, i64) -> i64,
+    ) -> Option<Word> {
+        match self {
+            Self::Checked => checked(a, b).filter(|value| i64::try_from(*value).is_ok()),
+            // truncating the operands first is what wrapping at 64 bits means
+            Self::Wrapping => Some(Word::from(wrapping(a as i64, b as i64))),
+            Self::Wide => checked(a, b),
+        }
+    }
+}
+
+impl fmt::Display for Arithmetic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Checked => f.write_str("checked"),
+            Self::Wrapping => f.write_str("wrapping"),
+            Self::Wide => f.write_str("wide"),
+        }
+    }
+}
+
+impl FromStr for Arithmetic {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "checked" => Ok(Self::Checked),
+            "wrapping" => Ok(Self::Wrapping),
+            "wide" => Ok(Self::Wide),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunState {
+    Halted,
+    NeedsInput,
+    /// Only returned when yielding on output; the value is not queued.
+    Output(Word),
+    /// A watch added with `add_watch` matched.
+    Watch(Hit),
+}
 
 #[derive(Clone, Debug)]
-pub(crate) struct Computer {
-    pub memory: Memory,
+pub struct Computer {
+    memory: Memory,
     pointer: usize,
-    input: VecDeque<isize>,
-    output: VecDeque<isize>,
+    input: VecDeque<Word>,
+    output: VecDeque<Word>,
+    input_device: Option<Device<dyn InputDevice + Send>>,
+    output_device: Option<Device<dyn OutputDevice + Send>>,
     yeild_on_output: bool,
-    yielded: bool,
     halted: bool,
-    relative_base: isize,
+    relative_base: Word,
+    arithmetic: Arithmetic,
+    trace: Option<Trace>,
+    cache: Option<DecodeCache>,
+    profile: Option<Profile>,
+    history: Option<History>,
+    watches: Watches,
+    instruction_set: Option<Arc<InstructionSet>>,
+    max_dense_len: usize,
 }
 
 impl Computer {
@@ -25,10 +152,19 @@ impl Computer {
             pointer: 0,
             input: VecDeque::new(),
             output: VecDeque::new(),
+            input_device: None,
+            output_device: None,
             yeild_on_output: false,
-            yielded: false,
             halted: false,
             relative_base: 0,
+            arithmetic: Arithmetic::default(),
+            trace: None,
+            cache: Some(DecodeCache::default()),
+            profile: None,
+            history: None,
+            watches: Watches::default(),
+            instruction_set: None,
+            max_dense_len: DEFAULT_MAX_DENSE_LEN,
         }
     }
 
@@ -36,123 +172,700 @@ impl Computer {
         self.yeild_on_output = val;
     }
 
-    pub fn set_input(&mut self, input: Vec<isize>) {
+    /// Writes that would grow dense memory past `len` cells fail with
+    /// `AddressOutOfRange`, so a program can't make the process allocate
+    /// without bound. Use sparse memory for programs that need far addresses.
+    pub fn set_max_dense_len(&mut self, len: usize) {
+        self.max_dense_len = len;
+    }
+
+    /// Fails if writing `address` would grow dense memory past the limit
+    /// set with `set_max_dense_len`.
+    pub fn check_write(&self, address: usize) -> Result<(), ComputerError> {
+        if self.memory.is_sparse() || address < self.memory.len().max(self.max_dense_len) {
+            Ok(())
+        } else {
+            Err(ComputerError::AddressOutOfRange {
+                address: address as Word,
+                pointer: self.pointer,
+            })
+        }
+    }
+
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
+
+    pub fn set_input(&mut self, input: Vec<Word>) {
         self.input = input.into();
     }
 
-    pub fn push_input(&mut self, input: isize) {
+    pub fn push_input(&mut self, input: Word) {
         self.input.push_back(input);
     }
 
-    pub fn get_output(&self) -> Vec<isize> {
+    pub fn get_output(&self) -> Vec<Word> {
         self.output.iter().copied().collect()
     }
 
-    pub fn next_output(&mut self) -> Option<isize> {
+    pub fn next_output(&mut self) -> Option<Word> {
         self.output.pop_front()
     }
 
+    /// Queues `line` as ASCII codes followed by a newline. Nothing is
+    /// queued if `line` isn't ASCII.
+    pub fn push_line(&mut self, line: &str) -> Result<(), NotAscii> {
+        self.input.extend(ascii::encode_line(line)?);
+        Ok(())
+    }
+
+    /// Drains the queued output as text.
+    pub fn take_ascii(&mut self) -> Ascii {
+        Ascii::decode(self.output.drain(..))
+    }
+
+    /// Reads input from `device` once the queued input runs out. Clones of
+    /// the computer share the device.
+    pub fn set_input_device<D: InputDevice + Send + 'static>(&mut self, device: D) {
+        self.input_device = Some(Device::input(device));
+    }
+
+    pub fn clear_input_device(&mut self) {
+        self.input_device = None;
+    }
+
+    /// Sends output to `device` instead of queueing it. Clones of the
+    /// computer share the device.
+    pub fn set_output_device<D: OutputDevice + Send + 'static>(&mut self, device: D) {
+        self.output_device = Some(Device::output(device));
+    }
+
+    pub fn clear_output_device(&mut self) {
+        self.output_device = None;
+    }
+
+    /// Sends every executed instruction to `sink`. The caller keeps its own
+    /// handle to read the sink back, e.g. to dump a `RingTrace` after an error.
+    pub fn set_trace_sink<S: TraceSink + Send + 'static>(&mut self, sink: Arc<Mutex<S>>) {
+        self.trace = Some(Trace::new(sink));
+    }
+
+    pub fn clear_trace_sink(&mut self) {
+        self.trace = None;
+    }
+
+    /// Enabling starts a fresh `Profile`; disabling drops it.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = enabled.then(Profile::default);
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Enables reverse execution: every executed instruction is logged so it
+    /// can be undone later. Enabling starts an empty history; disabling drops
+    /// it.
+    ///
+    /// Undoing an `Input` puts the value back at the front of the input
+    /// queue, so running forward again reads it again. Undoing an `Output`
+    /// takes its value back off the output queue if it is still there;
+    /// values already taken, yielded or sent to a device stay out.
+    pub fn set_history(&mut self, enabled: bool) {
+        self.history = enabled.then(History::default);
+    }
+
+    /// How many instructions can be undone.
+    pub fn history_len(&self) -> usize {
+        self.history.as_ref().map_or(0, History::len)
+    }
+
+    /// Undoes up to `count` instructions and returns how many were undone.
+    pub fn step_back(&mut self, count: usize) -> usize {
+        (0..count).take_while(|_| self.undo().is_some()).count()
+    }
+
+    /// Undoes instructions up to and including the most recent `Input`, and
+    /// returns how many were undone. Does nothing if the history has no
+    /// `Input`.
+    pub fn rewind_to_input(&mut self) -> Option<usize> {
+        let count = self
+            .history
+            .as_ref()?
+            .count_back_to(|entry| entry.input.is_some())?;
+        Some(self.step_back(count))
+    }
+
+    /// Undoes instructions up to and including the most recent `Output`.
+    pub fn rewind_to_output(&mut self) -> Option<usize> {
+        let count = self.history.as_ref()?.count_back_to(|entry| entry.output)?;
+        Some(self.step_back(count))
+    }
+
+    /// Decoded instructions are cached by pointer unless this is turned off.
+    /// An entry is only used while memory still holds the cells it was
+    /// decoded from, so self-modifying code still sees its own changes.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.cache = enabled.then(DecodeCache::default);
+    }
+
+    /// Decodes the opcodes registered in `set` as well as the standard ones.
+    /// Clones of the computer share the set.
+    pub fn set_instruction_set(&mut self, set: InstructionSet) {
+        self.instruction_set = Some(Arc::new(set));
+        if self.cache.is_some() {
+            self.cache = Some(DecodeCache::default());
+        }
+    }
+
+    pub fn instruction_set(&self) -> Option<&InstructionSet> {
+        self.instruction_set.as_deref()
+    }
+
+    /// Decodes every address up front, so clones of this machine start with
+    /// a warm cache.
+    pub fn predecode(&mut self) {
+        let Some(cache) = &mut self.cache else {
+            return;
+        };
+        let set = self.instruction_set.as_deref();
+        let mut pointer = self.memory.next_allocated(0);
+        while pointer < self.memory.len() {
+            if let Ok(instruction) = Instruction::decode_with(&self.memory, pointer, set) {
+                cache.insert(&self.memory, pointer, instruction);
+            }
+            pointer = self.memory.next_allocated(pointer + 1);
+        }
+    }
+
     pub fn is_halted(&self) -> bool {
         self.halted
     }
 
-    pub fn set_memory(&mut self, address: usize, value: isize) {
-        if self.memory.len() <= address {
-            self.memory.resize(address + 1, 0);
-        }
+    pub fn pointer(&self) -> usize {
+        self.pointer
+    }
+
+    pub fn relative_base(&self) -> Word {
+        self.relative_base
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
 
-        self.memory[address] = value;
+    pub fn set_memory(&mut self, address: usize, value: Word) {
+        self.memory.set(address, value);
     }
 
-    pub fn run(&mut self) {
-        self.yielded = false;
+    pub fn run(&mut self) -> Result<RunState, ComputerError> {
+        if !self.watches.is_empty() {
+            return self.run_watched();
+        }
         loop {
-            if self.yielded || self.halted {
-                return;
+            let step = self.step()?;
+            if let Some(state) = self.stop_state(step.as_ref()) {
+                return Ok(state);
             }
+        }
+    }
 
-            let mem_val = *self.memory.get(self.pointer).unwrap() as usize;
-            let opcode: Opcode = mem_val.into();
-            let mem_slice = &self.memory[self.pointer..];
-            let Some(instruction) = Instruction::from_opcode(opcode, mem_slice.iter()) else {
-                panic!("Invalid opcode")
+    /// `run` with watches, which are checked on entry and after every step.
+    fn run_watched(&mut self) -> Result<RunState, ComputerError> {
+        if !self.halted {
+            if let Some(hit) = self.watches.breakpoint(self.pointer) {
+                return Ok(RunState::Watch(hit));
+            }
+        }
+        loop {
+            let step = self.step()?;
+            if let Some(state) = self.stop_state(step.as_ref()) {
+                return Ok(state);
+            }
+            let hit = match &step {
+                Some(step) => self.watches.after(step),
+                None => None,
             };
+            let hit = hit.or_else(|| self.watches.breakpoint(self.pointer));
+            if let Some(hit) = hit {
+                return Ok(RunState::Watch(hit));
+            }
+        }
+    }
+
+    /// What `run` returns for the result of `step`, or `None` if it should
+    /// keep going.
+    fn stop_state(&self, step: Option<&Step>) -> Option<RunState> {
+        let Some(step) = step else {
+            return Some(if self.halted {
+                RunState::Halted
+            } else {
+                RunState::NeedsInput
+            });
+        };
 
-            self.execute_instr(instruction);
+        match step.instruction {
+            Instruction::Stop => Some(RunState::Halted),
+            Instruction::Output(_) if self.yeild_on_output => {
+                Some(RunState::Output(step.operands[0]))
+            }
+            _ => None,
+        }
+    }
+
+    /// Executes the instruction at the pointer. Returns `None` without doing
+    /// anything if the machine has halted or is waiting for input.
+    ///
+    /// When yielding on output, output values are not queued; they are only
+    /// reported in the step's operands.
+    pub fn step(&mut self) -> Result<Option<Step>, ComputerError> {
+        let pointer = self.pointer;
+        let relative_base = self.relative_base;
+        let result = self.execute();
+        if let Ok(Some(_)) = &result {
+            self.watches.resume();
+        }
+        if let (Err(error), Some(trace)) = (&result, &self.trace) {
+            let set = self.instruction_set.as_deref();
+            trace.record_fault(&Fault {
+                pointer,
+                relative_base,
+                instruction: Instruction::decode_with(&self.memory, pointer, set).ok(),
+                error: error.clone(),
+            });
         }
+        result
     }
 
-    fn execute_instr(&mut self, instruction: Instruction) {
-        match instruction {
+    fn execute(&mut self) -> Result<Option<Step>, ComputerError> {
+        if self.halted {
+            return Ok(None);
+        }
+
+        let pointer = self.pointer;
+        let relative_base = self.relative_base;
+        let memory_len = self.memory.len();
+        let instruction = self.decode(pointer)?;
+        let mut operands = Vec::with_capacity(2);
+        let mut write = None;
+
+        match &instruction {
             Instruction::Add(p1, p2, p3) => {
-                let op1 = p1.value(self);
-                let op2 = p2.value(self);
-                let result = op1 + op2;
-                self.set_memory(p3.as_pos(self), result);
+                let op1 = self.read(p1, &mut operands)?;
+                let op2 = self.read(p2, &mut operands)?;
+                let result = self.add(op1, op2)?;
+                write = Some(self.write(p3, result)?);
                 self.pointer += 4;
             }
             Instruction::Mult(p1, p2, p3) => {
-                let op1 = p1.value(self);
-                let op2 = p2.value(self);
-                let result = op1 * op2;
-                self.set_memory(p3.as_pos(self), result);
+                let op1 = self.read(p1, &mut operands)?;
+                let op2 = self.read(p2, &mut operands)?;
+                let result = self.mul(op1, op2)?;
+                write = Some(self.write(p3, result)?);
                 self.pointer += 4;
             }
             Instruction::Input(p1) => {
-                if let Some(value) = self.input.pop_front() {
-                    self.set_memory(p1.as_pos(self), value);
-                    self.pointer += 2;
-                } else {
-                    self.yielded = true;
-                }
+                self.write_address(p1)?;
+                let Some(value) = self.read_input() else {
+                    return Ok(None);
+                };
+                write = Some(self.write(p1, value)?);
+                self.pointer += 2;
             }
             Instruction::Output(p1) => {
-                let value = p1.value(self);
-                self.output.push_back(value);
+                let value = self.read(p1, &mut operands)?;
                 self.pointer += 2;
 
-                if self.yeild_on_output {
-                    self.yielded = true;
+                if !self.yeild_on_output {
+                    self.write_output(value);
                 }
             }
             Instruction::JumpIfTrue(p1, p2) => {
-                let value = p1.value(self);
+                let value = self.read(p1, &mut operands)?;
                 if value != 0 {
-                    self.pointer = p2.value(self) as usize;
+                    self.pointer = self.jump_target(p2, &mut operands)?;
                 } else {
                     self.pointer += 3;
                 }
             }
             Instruction::JumpIfFalse(p1, p2) => {
-                let value = p1.value(self);
+                let value = self.read(p1, &mut operands)?;
                 if value == 0 {
-                    self.pointer = p2.value(self) as usize;
+                    self.pointer = self.jump_target(p2, &mut operands)?;
                 } else {
                     self.pointer += 3;
                 }
             }
             Instruction::LessThan(p1, p2, p3) => {
-                let val1 = p1.value(self);
-                let val2 = p2.value(self);
+                let val1 = self.read(p1, &mut operands)?;
+                let val2 = self.read(p2, &mut operands)?;
                 let out = if val1 < val2 { 1 } else { 0 };
-                self.set_memory(p3.as_pos(self), out);
+                write = Some(self.write(p3, out)?);
                 self.pointer += 4;
             }
             Instruction::Equals(p1, p2, p3) => {
-                let val1 = p1.value(self);
-                let val2 = p2.value(self);
+                let val1 = self.read(p1, &mut operands)?;
+                let val2 = self.read(p2, &mut operands)?;
                 let out = if val1 == val2 { 1 } else { 0 };
-                self.set_memory(p3.as_pos(self), out);
+                write = Some(self.write(p3, out)?);
                 self.pointer += 4;
             }
             Instruction::RelativeBase(p1) => {
-                let val = p1.value(self);
-                self.relative_base += val;
+                let val = self.read(p1, &mut operands)?;
+                self.relative_base = self.add(self.relative_base, val)?;
                 self.pointer += 2;
             }
             Instruction::Stop => {
                 self.halted = true;
             }
+            Instruction::Custom {
+                code,
+                params,
+                write: target,
+                ..
+            } => {
+                let op = self
+                    .instruction_set
+                    .as_ref()
+                    .and_then(|set| set.get(*code))
+                    .cloned()
+                    .ok_or(ComputerError::InvalidOpcode {
+                        opcode: self.memory[pointer],
+                        pointer,
+                    })?;
+                for (index, param) in params.iter().enumerate() {
+                    if Some(index) != *target {
+                        self.read(param, &mut operands)?;
+                    }
+                }
+                let result = op.execute(&operands);
+                if let (Some(index), Some(value)) = (*target, result) {
+                    write = Some(self.write(&params[index], value)?);
+                }
+                self.pointer += instruction.size();
+            }
+        }
+
+        let reads = Step::read_params(&instruction, operands.len())
+            .into_iter()
+            .filter_map(|param| param.address(relative_base, self.arithmetic, pointer).ok())
+            .collect();
+        let step = Step {
+            pointer,
+            relative_base,
+            instruction,
+            operands,
+            reads,
+            write,
+            next_pointer: self.pointer,
+        };
+        if let Some(trace) = &self.trace {
+            trace.record(&step);
+        }
+        if let Some(profile) = &mut self.profile {
+            profile.record(&step);
+        }
+        if let Some(history) = &mut self.history {
+            let queued = matches!(step.instruction, Instruction::Output(_))
+                && !self.yeild_on_output
+                && self.output_device.is_none();
+            history.record(&step, memory_len, queued);
+        }
+
+        Ok(Some(step))
+    }
+
+    fn undo(&mut self) -> Option<()> {
+        let entry = self.history.as_mut()?.pop()?;
+        self.pointer = entry.pointer;
+        self.relative_base = entry.relative_base;
+        self.halted = false;
+        if let Some(write) = entry.write {
+            self.set_memory(write.address, write.old);
+        }
+        self.memory.truncate(entry.memory_len);
+        if let Some(value) = entry.input {
+            self.input.push_front(value);
+        }
+        // later outputs were undone first, so if this one is still queued
+        // it is at the back
+        if entry.queued {
+            self.output.pop_back();
+        }
+        Some(())
+    }
+
+    fn decode(&mut self, pointer: usize) -> Result<Instruction, ComputerError> {
+        let set = self.instruction_set.as_deref();
+        let Some(cache) = &mut self.cache else {
+            return Instruction::decode_with(&self.memory, pointer, set);
+        };
+        if let Some(instruction) = cache.get(&self.memory, pointer) {
+            return Ok(instruction.clone());
+        }
+        let instruction = Instruction::decode_with(&self.memory, pointer, set)?;
+        cache.insert(&self.memory, pointer, instruction.clone());
+        Ok(instruction)
+    }
+
+    fn read_input(&mut self) -> Option<Word> {
+        match self.input.pop_front() {
+            Some(value) => Some(value),
+            None => self.input_device.as_ref()?.read(),
+        }
+    }
+
+    fn write_output(&mut self, value: Word) {
+        match &self.output_device {
+            Some(device) => device.write(value),
+            None => self.output.push_back(value),
+        }
+    }
+
+    fn add(&self, op1: Word, op2: Word) -> Result<Word, ComputerError> {
+        self.arithmetic
+            .add(op1, op2)
+            .ok_or(ComputerError::Overflow {
+                pointer: self.pointer,
+            })
+    }
+
+    fn mul(&self, op1: Word, op2: Word) -> Result<Word, ComputerError> {
+        self.arithmetic
+            .mul(op1, op2)
+            .ok_or(ComputerError::Overflow {
+                pointer: self.pointer,
+            })
+    }
+
+    fn read(&self, param: &Param, operands: &mut Vec<Word>) -> Result<Word, ComputerError> {
+        let value = param.value(self)?;
+        operands.push(value);
+        Ok(value)
+    }
+
+    /// The address a write through `param` goes to, if it may be written.
+    fn write_address(&self, param: &Param) -> Result<usize, ComputerError> {
+        let address = param.as_pos(self)?;
+        self.check_write(address)?;
+        Ok(address)
+    }
+
+    fn write(&mut self, param: &Param, value: Word) -> Result<Write, ComputerError> {
+        let address = self.write_address(param)?;
+        let old = *self.memory.get(address).unwrap_or(&0);
+        self.set_memory(address, value);
+        Ok(Write {
+            address,
+            old,
+            new: value,
+        })
+    }
+
+    fn jump_target(&self, param: &Param, operands: &mut Vec<Word>) -> Result<usize, ComputerError> {
+        let address = self.read(param, operands)?;
+        instruction::to_address(address, self.pointer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        error::ComputerError,
+        instruction::{Instruction, Param},
+        memory::Memory,
+        step::{Step, Write},
+        Arithmetic, Computer, RunState, Word,
+    };
+
+    #[test]
+    fn test_run_state() {
+        let mut computer = Computer::new("3,9,4,9,3,9,4,9,99,0".to_string().into());
+        computer.set_yield_on_output(true);
+        assert_eq!(computer.run(), Ok(RunState::NeedsInput));
+        computer.push_input(7);
+        assert_eq!(computer.run(), Ok(RunState::Output(7)));
+        assert_eq!(computer.run(), Ok(RunState::NeedsInput));
+        computer.push_input(8);
+        assert_eq!(computer.run(), Ok(RunState::Output(8)));
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.get_output(), vec![]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut computer = Computer::new("1,0,0,0,42".to_string().into());
+        assert_eq!(
+            computer.run(),
+            Err(ComputerError::InvalidOpcode {
+                opcode: 42,
+                pointer: 4
+            })
+        );
+
+        let mut computer = Computer::new("301,0,0,0,99".to_string().into());
+        assert_eq!(
+            computer.run(),
+            Err(ComputerError::UnknownParamMode {
+                mode: 3,
+                pointer: 0
+            })
+        );
+
+        let mut computer = Computer::new("1101,1,1,0".to_string().into());
+        assert_eq!(
+            computer.run(),
+            Err(ComputerError::PointerOutOfBounds { pointer: 4 })
+        );
+
+        let mut computer = Computer::new("1,-3,0,0,99".to_string().into());
+        assert_eq!(
+            computer.run(),
+            Err(ComputerError::NegativeAddress {
+                address: -3,
+                pointer: 0
+            })
+        );
+
+        let mut computer = Computer::new("11101,1,1,0,99".to_string().into());
+        assert_eq!(
+            computer.run(),
+            Err(ComputerError::WriteToImmediate { pointer: 0 })
+        );
+
+        // writes far past the dense memory limit, which works on sparse memory
+        let program = "1101,1,1,1000000000000000,99";
+        let mut computer = Computer::new(program.parse().unwrap());
+        assert_eq!(
+            computer.run(),
+            Err(ComputerError::AddressOutOfRange {
+                address: 1_000_000_000_000_000,
+                pointer: 0
+            })
+        );
+        let mut computer =
+            Computer::new(Memory::sparse(program.parse::<Memory>().unwrap().to_vec()));
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.memory()[1_000_000_000_000_000], 2);
+
+        let mut computer = Computer::new("3,10,99".parse().unwrap());
+        computer.set_max_dense_len(8);
+        computer.push_input(1);
+        assert_eq!(
+            computer.run(),
+            Err(ComputerError::AddressOutOfRange {
+                address: 10,
+                pointer: 0
+            })
+        );
+        // the input is still queued
+        computer.set_max_dense_len(11);
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+
+        let program = format!("109,{},204,1,99", i64::MAX);
+        let mut computer = Computer::new(program.parse().unwrap());
+        assert_eq!(computer.run(), Err(ComputerError::Overflow { pointer: 2 }));
+    }
+
+    #[test]
+    fn test_step() {
+        let mut computer = Computer::new("1002,4,3,4,33".to_string().into());
+        let step = computer.step().unwrap().unwrap();
+        assert_eq!(
+            step,
+            Step {
+                pointer: 0,
+                relative_base: 0,
+                instruction: Instruction::Mult(Param::Pos(4), Param::Imm(3), Param::Pos(4)),
+                operands: vec![33, 3],
+                reads: vec![4],
+                write: Some(Write {
+                    address: 4,
+                    old: 33,
+                    new: 99
+                }),
+                next_pointer: 4,
+            }
+        );
+
+        let step = computer.step().unwrap().unwrap();
+        assert_eq!(step.instruction, Instruction::Stop);
+        assert_eq!(step.next_pointer, 4);
+        assert_eq!(computer.step(), Ok(None));
+    }
+
+    #[test]
+    fn test_memory_backends() {
+        let programs = [
+            "1,9,10,3,2,3,11,0,99,30,40,50",
+            "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
+            "3,100000,109,99990,204,10,99",
+        ];
+        for program in programs {
+            let memory: Memory = program.parse().unwrap();
+            let mut dense = Computer::new(memory.clone());
+            let mut sparse = Computer::new(memory.into_sparse());
+            for computer in [&mut dense, &mut sparse] {
+                computer.push_input(42);
+                assert_eq!(computer.run(), Ok(RunState::Halted));
+            }
+            assert_eq!(sparse.get_output(), dense.get_output());
+            assert_eq!(sparse.memory().to_vec(), dense.memory().to_vec());
         }
     }
+
+    #[test]
+    fn test_arithmetic() {
+        let program = format!("1101,{},1,0,1102,{},2,0,99", i64::MAX, i64::MAX);
+        let mut computer = Computer::new(program.parse().unwrap());
+        assert_eq!(computer.run(), Err(ComputerError::Overflow { pointer: 0 }));
+
+        let mut computer = Computer::new(program.parse().unwrap());
+        computer.set_arithmetic(Arithmetic::Wrapping);
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.memory()[0], Word::from(i64::MAX.wrapping_mul(2)));
+
+        let mut computer = Computer::new(program.parse().unwrap());
+        computer.set_arithmetic(Arithmetic::Wide);
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.memory()[0], Word::from(i64::MAX) * 2);
+
+        let program = format!("109,{},109,1,99", i64::MAX);
+        let mut computer = Computer::new(program.parse().unwrap());
+        assert_eq!(computer.run(), Err(ComputerError::Overflow { pointer: 2 }));
+        let mut computer = Computer::new(program.parse().unwrap());
+        computer.set_arithmetic(Arithmetic::Wide);
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.relative_base(), Word::from(i64::MAX) + 1);
+
+        let program = format!("1101,{},1,0,99", Word::MAX);
+        let mut computer = Computer::new(program.parse().unwrap());
+        computer.set_arithmetic(Arithmetic::Wide);
+        assert_eq!(computer.run(), Err(ComputerError::Overflow { pointer: 0 }));
+    }
+
+    #[test]
+    fn test_decode_cache() {
+        // runs the instruction at 0 twice, patching it from ADD to MUL in between
+        let program = "1,20,21,20,1005,22,18,1101,0,2,0,1101,0,1,22,1105,1,0,99,0,3,4,0";
+        let mut template = Computer::new(program.parse().unwrap());
+        template.predecode();
+        let mut uncached = template.clone();
+        uncached.set_decode_cache(false);
+        for mut computer in [template.clone(), template, uncached] {
+            assert_eq!(computer.run(), Ok(RunState::Halted));
+            assert_eq!(computer.memory()[20], 28);
+        }
+
+        // a clone patches a cached ADD into a MUL before running
+        let mut template = Computer::new("1101,3,3,0,99".parse().unwrap());
+        template.predecode();
+        let mut patched = template.clone();
+        patched.set_memory(0, 1102);
+        assert_eq!(patched.run(), Ok(RunState::Halted));
+        assert_eq!(patched.memory()[0], 9);
+        assert_eq!(template.run(), Ok(RunState::Halted));
+        assert_eq!(template.memory()[0], 6);
+    }
 }
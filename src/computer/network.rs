@@ -0,0 +1,263 @@
+use std::fmt;
+
+use super::{error::ComputerError, memory::Memory, Computer, Word};
+
+pub const DEFAULT_NAT_ADDRESS: Word = 255;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Packet {
+    pub destination: Word,
+    pub x: Word,
+    pub y: Word,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A computer sent a packet, possibly to the NAT.
+    Sent { from: usize, packet: Packet },
+    /// The network was idle, so the NAT sent its last packet to address 0.
+    Wake(Packet),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetworkError {
+    Computer {
+        address: usize,
+        error: ComputerError,
+    },
+    UnknownDestination {
+        from: usize,
+        packet: Packet,
+    },
+    /// The network is idle and the NAT has nothing to send, so nothing will
+    /// ever happen again.
+    Stalled,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Computer { address, error } => write!(f, "computer {}: {}", address, error),
+            Self::UnknownDestination { from, packet } => write!(
+                f,
+                "computer {} sent a packet to unknown address {}",
+                from, packet.destination
+            ),
+            Self::Stalled => f.write_str("network is idle and the NAT has no packet"),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// Computers that exchange `(destination, x, y)` packets through their
+/// output, plus a NAT that wakes the network up when it goes idle.
+///
+/// Scheduling is round-robin in address order, so runs are deterministic.
+/// The network is idle after a round in which no computer had input
+/// waiting, every computer was fed `-1`, and no packet was sent.
+#[derive(Clone, Debug)]
+pub struct Network {
+    computers: Vec<Computer>,
+    /// Output values that don't make up a whole packet yet, per computer.
+    partial: Vec<Vec<Word>>,
+    nat_address: Word,
+    nat: Option<Packet>,
+}
+
+impl Network {
+    /// Boots `size` copies of `program`. Each computer first reads its own
+    /// address.
+    pub fn new(program: &Memory, size: usize) -> Self {
+        let computers = (0..size)
+            .map(|address| {
+                let mut computer = Computer::new(program.clone());
+                computer.push_input(address as Word);
+                computer
+            })
+            .collect();
+        Self {
+            computers,
+            partial: vec![vec![]; size],
+            nat_address: DEFAULT_NAT_ADDRESS,
+            nat: None,
+        }
+    }
+
+    pub fn set_nat_address(&mut self, address: Word) {
+        self.nat_address = address;
+    }
+
+    pub fn computers(&self) -> &[Computer] {
+        &self.computers
+    }
+
+    /// The last packet sent to the NAT.
+    pub fn nat(&self) -> Option<Packet> {
+        self.nat
+    }
+
+    /// Gives every computer one turn: a computer with no input waiting is
+    /// fed `-1`, then it runs until it needs more. Packets are delivered as
+    /// soon as they are sent, so later computers see them in the same round.
+    pub fn round(&mut self) -> Result<Vec<Event>, NetworkError> {
+        let mut events = vec![];
+        let mut idle = true;
+        for address in 0..self.computers.len() {
+            let computer = &mut self.computers[address];
+            if computer.is_halted() {
+                continue;
+            }
+            if computer.input.is_empty() {
+                computer.push_input(-1);
+            } else {
+                idle = false;
+            }
+            computer
+                .run()
+                .map_err(|error| NetworkError::Computer { address, error })?;
+
+            while let Some(value) = self.computers[address].next_output() {
+                let partial = &mut self.partial[address];
+                partial.push(value);
+                if let [destination, x, y] = partial[..] {
+                    partial.clear();
+                    let packet = Packet { destination, x, y };
+                    self.deliver(address, packet)?;
+                    events.push(Event::Sent {
+                        from: address,
+                        packet,
+                    });
+                    idle = false;
+                }
+            }
+        }
+
+        if idle && self.partial.iter().all(Vec::is_empty) {
+            let packet = match self.nat {
+                Some(packet) if !self.computers.iter().all(Computer::is_halted) => packet,
+                _ => return Err(NetworkError::Stalled),
+            };
+            let packet = Packet {
+                destination: 0,
+                ..packet
+            };
+            self.deliver(self.computers.len(), packet)?;
+            events.push(Event::Wake(packet));
+        }
+        Ok(events)
+    }
+
+    /// Runs rounds until `f` returns `Some` for an event.
+    pub fn run_until<T>(
+        &mut self,
+        mut f: impl FnMut(&Event) -> Option<T>,
+    ) -> Result<T, NetworkError> {
+        loop {
+            for event in self.round()? {
+                if let Some(result) = f(&event) {
+                    return Ok(result);
+                }
+            }
+        }
+    }
+
+    fn deliver(&mut self, from: usize, packet: Packet) -> Result<(), NetworkError> {
+        if packet.destination == self.nat_address {
+            self.nat = Some(packet);
+            return Ok(());
+        }
+
+        let computer = usize::try_from(packet.destination)
+            .ok()
+            .and_then(|address| self.computers.get_mut(address))
+            .ok_or(NetworkError::UnknownDestination { from, packet })?;
+        computer.push_input(packet.x);
+        computer.push_input(packet.y);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, Network, NetworkError, Packet};
+    use crate::computer::{assembler::assemble, memory::Memory};
+
+    /// Address 0 sends `(1, 0, 1)`. Every computer forwards what it receives
+    /// to the next address, adding one to `y`.
+    fn relay() -> Memory {
+        let source = "
+                    IN [address]
+                    JT [address], wait
+                    OUT #1
+                    OUT #0
+                    OUT #1
+            wait:   IN [x]
+                    EQ [x], #-1, [empty]
+                    JT [empty], wait
+                    IN [y]
+                    ADD [address], #1, [next]
+                    ADD [y], #1, [y]
+                    OUT [next]
+                    OUT [x]
+                    OUT [y]
+                    JT #1, wait
+            address: .data 0
+            x:      .data 0
+            y:      .data 0
+            empty:  .data 0
+            next:   .data 0
+        ";
+        assemble(source).unwrap()
+    }
+
+    #[test]
+    fn test_nat() {
+        let mut network = Network::new(&relay(), 3);
+        network.set_nat_address(3);
+
+        let sent = |from, destination, y| Event::Sent {
+            from,
+            packet: Packet {
+                destination,
+                x: 0,
+                y,
+            },
+        };
+        assert_eq!(
+            network.round(),
+            Ok(vec![sent(0, 1, 1), sent(1, 2, 2), sent(2, 3, 3)])
+        );
+        assert_eq!(network.nat().map(|packet| packet.y), Some(3));
+
+        let mut woken = vec![];
+        let result = network.run_until(|event| match event {
+            Event::Wake(packet) => {
+                woken.push(packet.y);
+                (woken.len() == 2).then_some(packet.destination)
+            }
+            Event::Sent { .. } => None,
+        });
+        assert_eq!(result, Ok(0));
+        assert_eq!(woken, vec![3, 6]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut network = Network::new(&relay(), 3);
+        let packet = Packet {
+            destination: 3,
+            x: 0,
+            y: 3,
+        };
+        assert_eq!(
+            network.round(),
+            Err(NetworkError::UnknownDestination { from: 2, packet })
+        );
+
+        let listen = assemble("IN [0]\nloop: IN [0]\nJT #1, loop").unwrap();
+        let mut network = Network::new(&listen, 2);
+        assert_eq!(network.round(), Ok(vec![]));
+        assert_eq!(network.round(), Err(NetworkError::Stalled));
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{instruction::Instruction, step::Step, Word};
+
+/// Execution counts gathered while profiling is enabled on a `Computer`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub executed: u64,
+    /// Keyed by mnemonic.
+    pub by_instruction: BTreeMap<&'static str, u64>,
+    /// Count per address, with the instruction last executed there.
+    pub by_address: HashMap<usize, (u64, Instruction)>,
+    pub max_relative_base: Word,
+    /// Highest address read, written or executed.
+    pub max_address: usize,
+}
+
+impl Profile {
+    pub(crate) fn record(&mut self, step: &Step) {
+        self.executed += 1;
+        *self
+            .by_instruction
+            .entry(step.instruction.mnemonic())
+            .or_default() += 1;
+        let (count, instruction) = self
+            .by_address
+            .entry(step.pointer)
+            .or_insert_with(|| (0, step.instruction.clone()));
+        *count += 1;
+        if *instruction != step.instruction {
+            *instruction = step.instruction.clone();
+        }
+
+        self.max_relative_base = self.max_relative_base.max(step.relative_base);
+        let fetched = step.pointer + step.instruction.size() - 1;
+        let written = step.write.map(|write| write.address);
+        self.max_address = step
+            .reads
+            .iter()
+            .copied()
+            .chain(written)
+            .fold(self.max_address.max(fetched), usize::max);
+    }
+
+    /// The `top` most executed addresses, busiest first.
+    pub fn hot_spots(&self, top: usize) -> Vec<(usize, u64, &Instruction)> {
+        let mut spots: Vec<_> = self
+            .by_address
+            .iter()
+            .map(|(address, (count, instruction))| (*address, *count, instruction))
+            .collect();
+        spots.sort_by_key(|(address, count, _)| (u64::MAX - count, *address));
+        spots.truncate(top);
+        spots
+    }
+
+    /// Totals, counts per instruction, and the `top` hot spots annotated
+    /// with their instructions.
+    pub fn report(&self, top: usize) -> String {
+        let mut lines = vec![
+            format!("executed {} instructions", self.executed),
+            format!("max relative base {}", self.max_relative_base),
+            format!("max address touched {}", self.max_address),
+            String::new(),
+            format!("{:<8}{:>10}", "opcode", "count"),
+        ];
+
+        let mut by_instruction: Vec<_> = self.by_instruction.iter().collect();
+        by_instruction.sort_by_key(|(_, count)| u64::MAX - **count);
+        for (mnemonic, count) in by_instruction {
+            lines.push(format!("{:<8}{:>10}", mnemonic, count));
+        }
+
+        lines.push(String::new());
+        lines.push(format!("{:>7}{:>10}  instruction", "address", "count"));
+        for (address, count, instruction) in self.hot_spots(top) {
+            lines.push(format!("{:>7}{:>10}  {}", address, count, instruction));
+        }
+
+        lines
+            .into_iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::computer::{Computer, RunState};
+
+    #[test]
+    fn test_profile() {
+        // counts down from the input, printing each value through the relative base
+        let program = "109,19,3,17,1001,17,-1,17,204,-2,1005,17,4,99,0,0,0,0";
+        let mut computer = Computer::new(program.parse().unwrap());
+        assert!(computer.profile().is_none());
+        computer.set_profiling(true);
+        computer.push_input(3);
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.get_output(), vec![2, 1, 0]);
+
+        let profile = computer.profile().unwrap();
+        assert_eq!(profile.executed, 12);
+        assert_eq!(profile.by_instruction["ADD"], 3);
+        assert_eq!(profile.max_relative_base, 19);
+        assert_eq!(profile.max_address, 17);
+
+        let expected = "\
+executed 12 instructions
+max relative base 19
+max address touched 17
+
+opcode       count
+ADD              3
+JT               3
+OUT              3
+ARB              1
+HLT              1
+IN               1
+
+address     count  instruction
+      4         3  ADD [pos 17], #-1, [pos 17]
+      8         3  OUT [rel -2]
+     10         3  JT [pos 17], #4
+";
+        assert_eq!(profile.report(3), expected);
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, fs,
+    io::{self, BufRead, BufReader, BufWriter},
+    path::Path,
+    str::FromStr,
+};
+
+use super::{memory::Memory, Computer, Word};
+
+const MAGIC: &str = "intcode-snapshot";
+const VERSION: u32 = 1;
+
+/// Saves and restores the complete machine state as a line-based text file:
+///
+/// ```text
+/// intcode-snapshot 1
+/// pointer 12
+/// relative_base 0
+/// halted false
+/// yield_on_output true
+/// arithmetic checked
+/// input 5,6
+/// output
+/// memory 3,9,4,9,99
+/// ```
+///
+/// Machines with sparse memory store `sparse_memory <len> <address>=<value>,..`
+/// with only the non-zero cells instead of `memory`. Unknown and repeated
+/// keys are rejected.
+///
+/// Attached devices, trace sinks, profiles, history, watches and instruction
+/// sets are not part of the snapshot.
+impl Computer {
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        self.write_snapshot(&mut writer)?;
+        Ok(())
+    }
+
+    pub fn load_snapshot<P: AsRef<Path>>(path: P) -> Result<Computer, SnapshotError> {
+        Self::read_snapshot(BufReader::new(fs::File::open(path)?))
+    }
+
+    pub fn write_snapshot<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{} {}", MAGIC, VERSION)?;
+        writeln!(writer, "pointer {}", self.pointer)?;
+        writeln!(writer, "relative_base {}", self.relative_base)?;
+        writeln!(writer, "halted {}", self.halted)?;
+        writeln!(writer, "yield_on_output {}", self.yeild_on_output)?;
+        writeln!(writer, "arithmetic {}", self.arithmetic)?;
+        writeln!(writer, "input {}", join(&self.input))?;
+        writeln!(writer, "output {}", join(&self.output))?;
+        if self.memory.is_sparse() {
+            writeln!(
+                writer,
+                "sparse_memory {} {}",
+                self.memory.len(),
+                self.memory
+            )?;
+        } else {
+            writeln!(writer, "memory {}", self.memory)?;
+        }
+        writer.flush()
+    }
+
+    pub fn read_snapshot<R: BufRead>(reader: R) -> Result<Computer, SnapshotError> {
+        let mut lines = reader.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        match header.split_once(' ') {
+            Some((MAGIC, version)) if version.trim() == VERSION.to_string() => {}
+            Some((MAGIC, version)) => {
+                return Err(SnapshotError::UnsupportedVersion(
+                    version.trim().to_string(),
+                ))
+            }
+            _ => return Err(SnapshotError::format(1, "not an intcode snapshot")),
+        }
+
+        let mut fields: HashMap<String, (usize, String)> = HashMap::new();
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+            if fields.contains_key(key) {
+                return Err(SnapshotError::format(
+                    index + 2,
+                    format!("repeated `{}`", key),
+                ));
+            }
+            fields.insert(key.to_string(), (index + 2, value.trim().to_string()));
+        }
+
+        let mut field = |key: &str| {
+            fields
+                .remove(key)
+                .ok_or_else(|| SnapshotError::format(0, format!("missing `{}`", key)))
+        };
+        let pointer = parse(field("pointer")?)?;
+        let relative_base = parse(field("relative_base")?)?;
+        let halted = parse(field("halted")?)?;
+        let yeild_on_output = parse(field("yield_on_output")?)?;
+        let arithmetic = parse(field("arithmetic")?)?;
+        let input = parse_list(field("input")?)?;
+        let output = parse_list(field("output")?)?;
+        let memory = match field("sparse_memory") {
+            Ok(value) => parse_sparse(value)?,
+            Err(_) => Memory::new(parse_list(field("memory")?)?.into()),
+        };
+        if let Some((key, (line, _))) = fields.iter().min_by_key(|(_, (line, _))| *line) {
+            return Err(SnapshotError::format(
+                *line,
+                format!("unexpected `{}`", key),
+            ));
+        }
+
+        let mut computer = Computer::new(memory);
+        computer.pointer = pointer;
+        computer.relative_base = relative_base;
+        computer.halted = halted;
+        computer.yeild_on_output = yeild_on_output;
+        computer.arithmetic = arithmetic;
+        computer.input = input;
+        computer.output = output;
+        Ok(computer)
+    }
+}
+
+fn join(values: &VecDeque<Word>) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse<T: FromStr>((line, value): (usize, String)) -> Result<T, SnapshotError> {
+    value
+        .parse()
+        .map_err(|_| SnapshotError::format(line, format!("invalid value `{}`", value)))
+}
+
+fn parse_list((line, value): (usize, String)) -> Result<VecDeque<Word>, SnapshotError> {
+    if value.is_empty() {
+        return Ok(VecDeque::new());
+    }
+    value
+        .split(',')
+        .map(|item| parse((line, item.trim().to_string())))
+        .collect()
+}
+
+fn parse_sparse((line, value): (usize, String)) -> Result<Memory, SnapshotError> {
+    let (len, cells) = value.split_once(' ').unwrap_or((value.as_str(), ""));
+    let len: usize = parse((line, len.to_string()))?;
+    let mut memory = Memory::sparse(vec![]);
+    if len > 0 {
+        memory.set(len - 1, 0);
+    }
+    for cell in cells.split(',').filter(|cell| !cell.trim().is_empty()) {
+        let Some((address, value)) = cell.split_once('=') else {
+            return Err(SnapshotError::format(
+                line,
+                format!("invalid cell `{}`", cell),
+            ));
+        };
+        let address: usize = parse((line, address.trim().to_string()))?;
+        if address == usize::MAX {
+            return Err(SnapshotError::format(
+                line,
+                format!("address {} is out of range", address),
+            ));
+        }
+        memory.set(address, parse((line, value.trim().to_string()))?);
+    }
+    Ok(memory)
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    UnsupportedVersion(String),
+    /// `line` is 0 when the problem is a missing field.
+    Format {
+        line: usize,
+        message: String,
+    },
+}
+
+impl SnapshotError {
+    fn format(line: usize, message: impl Into<String>) -> Self {
+        Self::Format {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {}", version)
+            }
+            Self::Format { line: 0, message } => write!(f, "invalid snapshot: {}", message),
+            Self::Format { line, message } => {
+                write!(f, "invalid snapshot at line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SnapshotError;
+    use crate::computer::{memory::Memory, Computer, RunState};
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let program = "109,5,3,0,204,-5,3,1,4,1,99".to_string();
+        let mut computer = Computer::new(program.into());
+        computer.push_input(7);
+        assert_eq!(computer.run(), Ok(RunState::NeedsInput));
+
+        let mut buffer = vec![];
+        computer.write_snapshot(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.starts_with("intcode-snapshot 1\npointer 6\nrelative_base 5\n"));
+
+        let mut restored = Computer::read_snapshot(buffer.as_slice()).unwrap();
+        assert_eq!(restored.get_output(), vec![7]);
+        for machine in [&mut computer, &mut restored] {
+            machine.push_input(9);
+            assert_eq!(machine.run(), Ok(RunState::Halted));
+        }
+        assert_eq!(restored.get_output(), computer.get_output());
+        assert_eq!(restored.memory().to_vec(), computer.memory().to_vec());
+    }
+
+    #[test]
+    fn test_sparse_snapshot() {
+        let mut computer = Computer::new(Memory::sparse(vec![1101, 4, 5, 1000000, 99]));
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+
+        let mut buffer = vec![];
+        computer.write_snapshot(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.ends_with("sparse_memory 1000001 0=1101,1=4,2=5,3=1000000,4=99,1000000=9\n"));
+
+        let restored = Computer::read_snapshot(buffer.as_slice()).unwrap();
+        assert!(restored.memory().is_sparse());
+        assert_eq!(restored.memory().len(), 1000001);
+        assert_eq!(restored.memory()[1000000], 9);
+    }
+
+    #[test]
+    fn test_snapshot_errors() {
+        let result = Computer::read_snapshot("intcode-snapshot 7\n".as_bytes());
+        assert!(matches!(result, Err(SnapshotError::UnsupportedVersion(v)) if v == "7"));
+
+        let result = Computer::read_snapshot("1,2,3\n".as_bytes());
+        assert!(matches!(result, Err(SnapshotError::Format { line: 1, .. })));
+
+        let text = "intcode-snapshot 1\npointer x\n";
+        let result = Computer::read_snapshot(text.as_bytes());
+        assert!(matches!(result, Err(SnapshotError::Format { line: 2, .. })));
+
+        let mut buffer = vec![];
+        Computer::new("99".parse().unwrap())
+            .write_snapshot(&mut buffer)
+            .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        for (text, line) in [
+            (text.replace("input \n", "input \ninput 1\n"), 8),
+            (format!("{}colour red\n", text), 10),
+            (text.replace("arithmetic checked\n", ""), 0),
+        ] {
+            let result = Computer::read_snapshot(text.as_bytes());
+            assert!(
+                matches!(result, Err(SnapshotError::Format { line: l, .. }) if l == line),
+                "{:?}",
+                result
+            );
+        }
+    }
+}
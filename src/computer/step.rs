@@ -0,0 +1,67 @@
+use std::fmt;
+
+use super::{
+    instruction::{Instruction, Param},
+    Word,
+};
+
+/// Everything that happened while executing a single instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub pointer: usize,
+    /// The relative base in effect when the instruction ran.
+    pub relative_base: Word,
+    pub instruction: Instruction,
+    /// Values of the parameters the instruction read, in parameter order.
+    /// Write targets are reported in `write` instead.
+    pub operands: Vec<Word>,
+    /// Addresses of the parameters the instruction read, in parameter order.
+    /// Immediates are skipped, as is the target of a jump that wasn't taken.
+    pub reads: Vec<usize>,
+    pub write: Option<Write>,
+    pub next_pointer: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Write {
+    pub address: usize,
+    pub old: Word,
+    pub new: Word,
+}
+
+impl Step {
+    /// The parameters behind `count` operands of `instruction`: write
+    /// targets and the target of a jump that wasn't taken are skipped.
+    pub(crate) fn read_params(instruction: &Instruction, count: usize) -> Vec<&Param> {
+        let mut params = instruction.params();
+        match instruction {
+            Instruction::Input(_) => params.clear(),
+            Instruction::Add(..)
+            | Instruction::Mult(..)
+            | Instruction::LessThan(..)
+            | Instruction::Equals(..) => params.truncate(2),
+            Instruction::Custom {
+                write: Some(write), ..
+            } => {
+                params.remove(*write);
+            }
+            _ => {}
+        }
+        params.truncate(count);
+        params
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>6} rb={:<6} {} operands={:?}",
+            self.pointer, self.relative_base, self.instruction, self.operands
+        )?;
+        if let Some(write) = &self.write {
+            write!(f, " [{}] {} -> {}", write.address, write.old, write.new)?;
+        }
+        write!(f, " next={}", self.next_pointer)
+    }
+}
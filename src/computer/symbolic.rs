@@ -0,0 +1,470 @@
+use std::{collections::HashMap, fmt, ops::RangeInclusive, rc::Rc};
+
+use itertools::Itertools;
+
+use super::{
+    instruction::{Instruction, Param},
+    memory::Memory,
+    Arithmetic, Computer, Word,
+};
+
+/// Symbolic runs give up after this many instructions.
+const MAX_STEPS: usize = 1_000_000;
+/// The fallback search runs the program once per assignment, so each run
+/// gets a smaller budget.
+const MAX_SEARCH_STEPS: usize = 100_000;
+/// A read through a symbolic address copies memory as it was, so it gives
+/// up on memory longer than this.
+const MAX_LOAD_LEN: usize = 1 << 16;
+/// Symbolic runs give up after building this many expression nodes, which
+/// also bounds how deep `eval` and `degree` recurse.
+const MAX_NODES: usize = 4_096;
+/// The policy of the machines `search` runs, which expressions follow too.
+const ARITHMETIC: Arithmetic = Arithmetic::Checked;
+
+/// A memory cell whose initial value is unknown, and the values it may take.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub address: usize,
+    pub domain: RangeInclusive<Word>,
+}
+
+/// A cell's value in terms of the symbols.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    Const(Word),
+    /// Index into the symbols the program was executed with.
+    Symbol(usize),
+    Add(Rc<Expr>, Rc<Expr>),
+    Mul(Rc<Expr>, Rc<Expr>),
+    /// A read through an address that depends on the symbols, from memory
+    /// as it was at the time.
+    Load(Rc<Expr>, Rc<Vec<Rc<Expr>>>),
+}
+
+impl Expr {
+    /// `None` if the machine would stop with an error instead, on overflow
+    /// or a negative address.
+    pub fn eval(&self, values: &[Word]) -> Option<Word> {
+        self.eval_shared(values, &mut HashMap::new())
+    }
+
+    /// Nodes are shared, so each is evaluated once and looked up in `memo`
+    /// after that.
+    fn eval_shared(
+        &self,
+        values: &[Word],
+        memo: &mut HashMap<*const Expr, Option<Word>>,
+    ) -> Option<Word> {
+        match self {
+            Self::Const(value) => return Some(*value),
+            Self::Symbol(index) => return Some(values[*index]),
+            _ => {}
+        }
+        if let Some(&value) = memo.get(&(self as *const Expr)) {
+            return value;
+        }
+        let value = match self {
+            Self::Add(a, b) => a
+                .eval_shared(values, memo)
+                .zip(b.eval_shared(values, memo))
+                .and_then(|(a, b)| ARITHMETIC.add(a, b)),
+            Self::Mul(a, b) => a
+                .eval_shared(values, memo)
+                .zip(b.eval_shared(values, memo))
+                .and_then(|(a, b)| ARITHMETIC.mul(a, b)),
+            Self::Load(address, memory) => {
+                let address = address.eval_shared(values, memo);
+                match address.and_then(|address| usize::try_from(address).ok()) {
+                    Some(address) => match memory.get(address) {
+                        Some(cell) => cell.eval_shared(values, memo),
+                        None => Some(0),
+                    },
+                    None => None,
+                }
+            }
+            Self::Const(_) | Self::Symbol(_) => unreachable!(),
+        };
+        memo.insert(self, value);
+        value
+    }
+
+    /// The degree of the polynomial in `symbol`, or `None` if the symbol
+    /// is read through a `Load`.
+    fn degree(&self, symbol: usize) -> Option<u32> {
+        self.degree_shared(symbol, &mut HashMap::new())
+    }
+
+    fn degree_shared(
+        &self,
+        symbol: usize,
+        memo: &mut HashMap<*const Expr, Option<u32>>,
+    ) -> Option<u32> {
+        match self {
+            Self::Const(_) => return Some(0),
+            Self::Symbol(index) => return Some(u32::from(*index == symbol)),
+            _ => {}
+        }
+        if let Some(&degree) = memo.get(&(self as *const Expr)) {
+            return degree;
+        }
+        let degree = match self {
+            Self::Add(a, b) => a
+                .degree_shared(symbol, memo)
+                .zip(b.degree_shared(symbol, memo))
+                .map(|(a, b)| a.max(b)),
+            Self::Mul(a, b) => a
+                .degree_shared(symbol, memo)
+                .zip(b.degree_shared(symbol, memo))
+                .map(|(a, b)| a + b),
+            Self::Load(address, memory) => {
+                let constant = address.degree_shared(symbol, memo) == Some(0)
+                    && memory
+                        .iter()
+                        .all(|cell| cell.degree_shared(symbol, memo) == Some(0));
+                constant.then_some(0)
+            }
+            Self::Const(_) | Self::Symbol(_) => unreachable!(),
+        };
+        memo.insert(self, degree);
+        degree
+    }
+
+    fn as_const(&self) -> Option<Word> {
+        match self {
+            Self::Const(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Const(value) => write!(f, "{}", value),
+            Self::Symbol(index) => write!(f, "s{}", index),
+            Self::Add(a, b) => write!(f, "({} + {})", a, b),
+            Self::Mul(a, b) => write!(f, "({} * {})", a, b),
+            Self::Load(address, _) => write!(f, "[{}]", address),
+        }
+    }
+}
+
+/// Runs `program` with the symbols' cells unknown, and returns `memory[0]`
+/// when it halts. `Add` and `Mult` build expressions; anything else that
+/// depends on a symbol, such as a jump, a write through a symbolic address,
+/// or input and output, gives `None`, as does a run that doesn't halt
+/// within `MAX_STEPS` instructions, builds more than `MAX_NODES`
+/// expression nodes, or reads through a symbolic address from memory
+/// longer than `MAX_LOAD_LEN`.
+pub fn execute(program: &Memory, symbols: &[Symbol]) -> Option<Rc<Expr>> {
+    let mut executor = Executor {
+        concrete: program.clone(),
+        symbolic: HashMap::new(),
+        pointer: 0,
+        relative_base: 0,
+        snapshot: None,
+        nodes: 0,
+    };
+    for (index, symbol) in symbols.iter().enumerate() {
+        executor.set(symbol.address, Rc::new(Expr::Symbol(index)));
+    }
+    executor.run()
+}
+
+/// Finds values for the symbols that make `program` halt with `target` in
+/// `memory[0]`, the first in lexicographic order. Uses the expression from
+/// `execute` when there is one, and otherwise runs the program for every
+/// assignment. A run that doesn't halt within `MAX_SEARCH_STEPS`
+/// instructions doesn't count as a solution.
+pub fn solve(program: &Memory, symbols: &[Symbol], target: Word) -> Option<Vec<Word>> {
+    match execute(program, symbols) {
+        Some(expr) => solve_expr(&expr, symbols, target),
+        None => search(program, symbols, target),
+    }
+}
+
+/// Enumerates every symbol but the last. When the expression is affine in
+/// the last one, its value is solved for directly.
+fn solve_expr(expr: &Expr, symbols: &[Symbol], target: Word) -> Option<Vec<Word>> {
+    let (last, rest) = symbols.split_last()?;
+    let index = rest.len();
+    if expr.degree(index).is_none_or(|degree| degree > 1) {
+        return assignments(symbols).find(|values| expr.eval(values) == Some(target));
+    }
+
+    assignments(rest).find_map(|mut values| {
+        values.push(0);
+        let offset = expr.eval(&values);
+        values[index] = 1;
+        let slope = expr.eval(&values).zip(offset);
+        let slope = slope.and_then(|(one, zero)| one.checked_sub(zero));
+        let distance = offset.and_then(|offset| target.checked_sub(offset));
+        values[index] = match (slope, distance) {
+            (Some(0), _) => *last.domain.start(),
+            (Some(slope), Some(distance)) if distance % slope == 0 => distance / slope,
+            _ => return None,
+        };
+        let solved = last.domain.contains(&values[index]) && expr.eval(&values) == Some(target);
+        solved.then_some(values)
+    })
+}
+
+fn search(program: &Memory, symbols: &[Symbol], target: Word) -> Option<Vec<Word>> {
+    let mut template = Computer::new(program.clone());
+    template.set_arithmetic(ARITHMETIC);
+    assignments(symbols).find(|values| {
+        let mut computer = template.clone();
+        for (symbol, value) in symbols.iter().zip(values) {
+            computer.set_memory(symbol.address, *value);
+        }
+        for _ in 0..MAX_SEARCH_STEPS {
+            if !matches!(computer.step(), Ok(Some(_))) {
+                break;
+            }
+        }
+        computer.is_halted() && computer.memory().first() == Some(&target)
+    })
+}
+
+fn assignments(symbols: &[Symbol]) -> impl Iterator<Item = Vec<Word>> + '_ {
+    symbols
+        .iter()
+        .map(|symbol| symbol.domain.clone())
+        .multi_cartesian_product()
+}
+
+struct Executor {
+    /// Symbolic cells hold 0 here.
+    concrete: Memory,
+    symbolic: HashMap<usize, Rc<Expr>>,
+    pointer: usize,
+    relative_base: Word,
+    /// Memory as `Load`s see it, shared until the next write.
+    snapshot: Option<Rc<Vec<Rc<Expr>>>>,
+    /// Expression nodes built so far.
+    nodes: usize,
+}
+
+impl Executor {
+    fn run(mut self) -> Option<Rc<Expr>> {
+        for _ in 0..MAX_STEPS {
+            if self.symbolic.contains_key(&self.pointer) {
+                return None;
+            }
+            let instruction = Instruction::decode(&self.concrete, self.pointer).ok()?;
+            match &instruction {
+                Instruction::Add(p1, p2, p3) => {
+                    let (a, b) = (self.read(0, p1)?, self.read(1, p2)?);
+                    let value = self.add(a, b)?;
+                    self.write(2, p3, value)?;
+                }
+                Instruction::Mult(p1, p2, p3) => {
+                    let (a, b) = (self.read(0, p1)?, self.read(1, p2)?);
+                    let value = self.mul(a, b)?;
+                    self.write(2, p3, value)?;
+                }
+                Instruction::JumpIfTrue(p1, p2) | Instruction::JumpIfFalse(p1, p2) => {
+                    let value = self.read(0, p1)?.as_const()?;
+                    let jump = matches!(instruction, Instruction::JumpIfTrue(..));
+                    if (value != 0) == jump {
+                        let target = self.read(1, p2)?.as_const()?;
+                        self.pointer = usize::try_from(target).ok()?;
+                        continue;
+                    }
+                }
+                Instruction::LessThan(p1, p2, p3) | Instruction::Equals(p1, p2, p3) => {
+                    let a = self.read(0, p1)?.as_const()?;
+                    let b = self.read(1, p2)?.as_const()?;
+                    let result = match instruction {
+                        Instruction::LessThan(..) => a < b,
+                        _ => a == b,
+                    };
+                    self.write(2, p3, Rc::new(Expr::Const(Word::from(result))))?;
+                }
+                Instruction::RelativeBase(p1) => {
+                    let offset = self.read(0, p1)?.as_const()?;
+                    self.relative_base = ARITHMETIC.add(self.relative_base, offset)?;
+                }
+                Instruction::Input(_) | Instruction::Output(_) | Instruction::Custom { .. } => {
+                    return None
+                }
+                Instruction::Stop => return Some(self.cell(0)),
+            }
+            self.pointer += instruction.size();
+        }
+        None
+    }
+
+    fn cell(&self, address: usize) -> Rc<Expr> {
+        match self.symbolic.get(&address) {
+            Some(expr) => Rc::clone(expr),
+            None => Rc::new(Expr::Const(*self.concrete.get(address).unwrap_or(&0))),
+        }
+    }
+
+    fn set(&mut self, address: usize, expr: Rc<Expr>) {
+        self.snapshot = None;
+        match expr.as_const() {
+            Some(value) => {
+                self.symbolic.remove(&address);
+                self.concrete.set(address, value);
+            }
+            None => {
+                self.concrete.set(address, 0);
+                self.symbolic.insert(address, expr);
+            }
+        }
+    }
+
+    /// The address parameter `index` of the current instruction refers to.
+    /// The parameter's own cell may be symbolic.
+    fn address(&mut self, index: usize, param: &Param) -> Option<Rc<Expr>> {
+        let raw = self.cell(self.pointer + 1 + index);
+        match param {
+            Param::Pos(_) => Some(raw),
+            Param::Rel(_) => self.add(Rc::new(Expr::Const(self.relative_base)), raw),
+            Param::Imm(_) => None,
+        }
+    }
+
+    fn read(&mut self, index: usize, param: &Param) -> Option<Rc<Expr>> {
+        if let Param::Imm(_) = param {
+            return Some(self.cell(self.pointer + 1 + index));
+        }
+        let address = self.address(index, param)?;
+        match address.as_const() {
+            Some(address) => Some(self.cell(usize::try_from(address).ok()?)),
+            None if self.concrete.len() > MAX_LOAD_LEN => None,
+            None => {
+                let memory = match &self.snapshot {
+                    Some(memory) => Rc::clone(memory),
+                    None => {
+                        let memory = (0..self.concrete.len()).map(|cell| self.cell(cell));
+                        Rc::clone(self.snapshot.insert(Rc::new(memory.collect())))
+                    }
+                };
+                self.node(Expr::Load(address, memory))
+            }
+        }
+    }
+
+    fn write(&mut self, index: usize, param: &Param, value: Rc<Expr>) -> Option<()> {
+        let address = self.address(index, param)?.as_const()?;
+        self.set(usize::try_from(address).ok()?, value);
+        Some(())
+    }
+
+    /// Folds constants, so `None` means the machine would overflow, or the
+    /// expression got too large.
+    fn add(&mut self, a: Rc<Expr>, b: Rc<Expr>) -> Option<Rc<Expr>> {
+        match (a.as_const(), b.as_const()) {
+            (Some(a), Some(b)) => Some(Rc::new(Expr::Const(ARITHMETIC.add(a, b)?))),
+            (Some(0), _) => Some(b),
+            (_, Some(0)) => Some(a),
+            _ => self.node(Expr::Add(a, b)),
+        }
+    }
+
+    fn mul(&mut self, a: Rc<Expr>, b: Rc<Expr>) -> Option<Rc<Expr>> {
+        match (a.as_const(), b.as_const()) {
+            (Some(a), Some(b)) => Some(Rc::new(Expr::Const(ARITHMETIC.mul(a, b)?))),
+            (Some(1), _) => Some(b),
+            (_, Some(1)) => Some(a),
+            _ => self.node(Expr::Mul(a, b)),
+        }
+    }
+
+    fn node(&mut self, expr: Expr) -> Option<Rc<Expr>> {
+        self.nodes += 1;
+        (self.nodes <= MAX_NODES).then(|| Rc::new(expr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{execute, search, solve, Symbol, MAX_LOAD_LEN, MAX_NODES};
+    use crate::computer::memory::Memory;
+
+    fn symbols(addresses: &[usize]) -> Vec<Symbol> {
+        addresses
+            .iter()
+            .map(|&address| Symbol {
+                address,
+                domain: 0..=20,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_symbolic() {
+        // [0] = 3 * noun + verb + noun + verb, after a dead read through
+        // noun and verb as addresses
+        let program: Memory = "1,0,0,3,1,1,2,3,2,1,21,0,1,0,2,0,1,0,3,0,99,3"
+            .parse()
+            .unwrap();
+        let symbols = symbols(&[1, 2]);
+        let expr = execute(&program, &symbols).unwrap();
+        assert_eq!(expr.to_string(), "(((s0 * 3) + s1) + (s0 + s1))");
+        assert_eq!(expr.eval(&[1, 2]), Some(8));
+        assert_eq!(solve(&program, &symbols, 38), Some(vec![0, 19]));
+        assert_eq!(search(&program, &symbols, 38), Some(vec![0, 19]));
+        assert_eq!(solve(&program, &symbols, 1000), None);
+
+        let program: Memory = "1,0,0,0,99".parse().unwrap();
+        let expr = execute(&program, &symbols).unwrap();
+        assert_eq!(expr.to_string(), "([s0] + [s1])");
+        assert_eq!(solve(&program, &symbols, 100), Some(vec![0, 4]));
+        assert_eq!(search(&program, &symbols, 100), Some(vec![0, 4]));
+    }
+
+    #[test]
+    fn test_fallback() {
+        // [0] = 5 if noun is 4, and 0 otherwise
+        let program: Memory = "1108,0,4,0,1005,0,8,99,1101,2,3,0,99".parse().unwrap();
+        let symbols = symbols(&[1]);
+        assert_eq!(execute(&program, &symbols), None);
+        assert_eq!(solve(&program, &symbols, 5), Some(vec![4]));
+
+        let program: Memory = "1101,0,7,0,99".parse().unwrap();
+        assert!(execute(&program, &symbols).is_some());
+        assert_eq!(solve(&program, &symbols, 10), Some(vec![3]));
+
+        // loops forever unless noun is 0
+        let program: Memory = "1105,0,0,99".parse().unwrap();
+        let symbol = Symbol {
+            address: 1,
+            domain: 0..=5,
+        };
+        assert_eq!(solve(&program, &[symbol], 7), None);
+    }
+
+    #[test]
+    fn test_limits() {
+        // moves the relative base past the end of the 64-bit range
+        let program: Memory = format!("109,{},109,1,99", i64::MAX).parse().unwrap();
+        assert_eq!(execute(&program, &symbols(&[5])), None);
+
+        // [0] = [noun] + 1, in memory too long to copy
+        let mut program: Memory = "1,0,0,0,99".parse().unwrap();
+        program.set(MAX_LOAD_LEN, 1);
+        assert_eq!(execute(&program, &symbols(&[1])), None);
+        assert_eq!(solve(&program, &symbols(&[1]), 1), Some(vec![2]));
+
+        // doubles noun 40 times, sharing each node with itself
+        let source = "1,165,165,165,".repeat(40) + "1001,165,0,0,99,0";
+        let program: Memory = source.parse().unwrap();
+        let expr = execute(&program, &symbols(&[165])).unwrap();
+        assert_eq!(expr.eval(&[3]), Some(3 << 40));
+        assert_eq!(solve(&program, &symbols(&[165]), 3 << 40), Some(vec![3]));
+
+        // [0] = noun + MAX_NODES + 1, one node per addition
+        let noun = 4 * (MAX_NODES + 1) + 5;
+        let source = format!("1001,{},1,{},", noun, noun).repeat(MAX_NODES + 1)
+            + &format!("1001,{},0,0,99,0", noun);
+        let program: Memory = source.parse().unwrap();
+        assert_eq!(execute(&program, &symbols(&[noun])), None);
+        let target = (MAX_NODES + 8) as i128;
+        assert_eq!(solve(&program, &symbols(&[noun]), target), Some(vec![7]));
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use super::{error::ComputerError, machine::Machine, RunState, Word};
+
+/// A machine running on its own thread, with the channel ends to talk to it.
+pub struct Spawned<M> {
+    pub input: Sender<Word>,
+    pub output: Receiver<Word>,
+    pub handle: JoinHandle<Exit<M>>,
+}
+
+/// How a spawned machine's thread ended.
+#[derive(Debug)]
+pub struct Exit<M> {
+    /// `Halted`, `NeedsInput` if every input sender was dropped while the
+    /// machine waited for input, or the error that stopped it.
+    pub result: Result<RunState, ComputerError>,
+    /// The machine as it was when it stopped, with its channels detached.
+    pub machine: M,
+}
+
+/// Runs `machine` on a new thread with fresh channels for input and output.
+pub fn spawn<M: Machine + Send + 'static>(machine: M) -> Spawned<M> {
+    let (input, receiver) = mpsc::channel();
+    let (sender, output) = mpsc::channel();
+    Spawned {
+        input,
+        output,
+        handle: spawn_with(machine, receiver, sender),
+    }
+}
+
+/// Runs `machine` on a new thread, reading input from `input` after any
+/// values already queued, and sending every output value to `output`.
+/// Input reads block, so the machine never yields. Both channel ends are
+/// dropped when the thread ends, which lets a downstream receiver finish.
+pub fn spawn_with<M: Machine + Send + 'static>(
+    mut machine: M,
+    input: Receiver<Word>,
+    output: Sender<Word>,
+) -> JoinHandle<Exit<M>> {
+    thread::spawn(move || {
+        machine.set_yield_on_output(false);
+        machine.set_input_device(Box::new(input));
+        machine.set_output_device(Box::new(output));
+        let result = machine.run();
+        machine.clear_input_device();
+        machine.clear_output_device();
+        Exit { result, machine }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::spawn;
+    use crate::computer::{error::ComputerError, Computer, RunState};
+
+    #[test]
+    fn test_spawn() {
+        let doubler = Computer::new("3,11,102,2,11,11,4,11,1105,1,0,0".parse().unwrap());
+        let spawned = spawn(doubler);
+        for value in 1..=3 {
+            spawned.input.send(value).unwrap();
+        }
+        drop(spawned.input);
+        assert_eq!(spawned.output.iter().collect::<Vec<_>>(), vec![2, 4, 6]);
+        let exit = spawned.handle.join().unwrap();
+        assert_eq!(exit.result, Ok(RunState::NeedsInput));
+        assert!(!exit.machine.is_halted());
+
+        let mut computer = Computer::new("3,0,4,0,99".parse().unwrap());
+        computer.push_input(5);
+        let spawned = spawn(computer);
+        let exit = spawned.handle.join().unwrap();
+        assert_eq!(exit.result, Ok(RunState::Halted));
+        assert_eq!(exit.machine.memory()[0], 5);
+        assert_eq!(spawned.output.recv(), Ok(5));
+
+        let spawned = spawn(Computer::new("1,0,0,0,42".parse().unwrap()));
+        assert_eq!(
+            spawned.handle.join().unwrap().result,
+            Err(ComputerError::InvalidOpcode {
+                opcode: 42,
+                pointer: 4
+            })
+        );
+    }
+}
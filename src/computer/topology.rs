@@ -0,0 +1,256 @@
+use std::fmt;
+
+use super::{error::ComputerError, machine::Machine, Word};
+
+/// Where an edge delivers a machine's output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Port {
+    Machine(usize),
+    /// Collected by the topology, see `Topology::sink`.
+    Sink,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TopologyError {
+    Machine {
+        id: usize,
+        error: ComputerError,
+    },
+    /// These machines wait for input that no running machine will send.
+    Deadlock {
+        blocked: Vec<usize>,
+    },
+    /// No machine was added with this id.
+    UnknownMachine(usize),
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Machine { id, error } => write!(f, "machine {}: {}", id, error),
+            Self::Deadlock { blocked } => write!(f, "deadlock, blocked machines {:?}", blocked),
+            Self::UnknownMachine(id) => write!(f, "no machine with id {}", id),
+        }
+    }
+}
+
+impl std::error::Error for TopologyError {}
+
+/// Machines wired output to input by directed edges, run on the current
+/// thread.
+///
+/// Every output value is copied to each of its machine's edges. A machine
+/// without edges keeps its output, which can be read back through
+/// `machines`.
+#[derive(Debug)]
+pub struct Topology<M> {
+    machines: Vec<M>,
+    edges: Vec<Vec<Port>>,
+    /// Machines that have input they haven't run on yet.
+    ready: Vec<bool>,
+    sink: Vec<Word>,
+}
+
+impl<M: Machine> Topology<M> {
+    pub fn new() -> Self {
+        Self {
+            machines: vec![],
+            edges: vec![],
+            ready: vec![],
+            sink: vec![],
+        }
+    }
+
+    /// Each machine feeds the next, and the last feeds the sink.
+    pub fn chain(machines: impl IntoIterator<Item = M>) -> Self {
+        let mut topology = Self::new();
+        for machine in machines {
+            let id = topology.add(machine);
+            if id > 0 {
+                topology.edges[id - 1].push(Port::Machine(id));
+            }
+        }
+        if let Some(last) = topology.edges.last_mut() {
+            last.push(Port::Sink);
+        }
+        topology
+    }
+
+    /// A chain whose last machine also feeds the first.
+    pub fn ring(machines: impl IntoIterator<Item = M>) -> Self {
+        let mut topology = Self::chain(machines);
+        if let Some(last) = topology.edges.last_mut() {
+            last.push(Port::Machine(0));
+        }
+        topology
+    }
+
+    /// A chain, closed into a ring when `feedback` is set.
+    pub fn pipeline(machines: impl IntoIterator<Item = M>, feedback: bool) -> Self {
+        if feedback {
+            Self::ring(machines)
+        } else {
+            Self::chain(machines)
+        }
+    }
+
+    /// Adds a machine and returns its id. Machines don't yield on output
+    /// while in a topology.
+    pub fn add(&mut self, mut machine: M) -> usize {
+        machine.set_yield_on_output(false);
+        self.machines.push(machine);
+        self.edges.push(vec![]);
+        self.ready.push(true);
+        self.machines.len() - 1
+    }
+
+    pub fn connect(&mut self, from: usize, to: Port) -> Result<(), TopologyError> {
+        self.check(from)?;
+        if let Port::Machine(to) = to {
+            self.check(to)?;
+        }
+        self.edges[from].push(to);
+        Ok(())
+    }
+
+    pub fn push_input(&mut self, id: usize, value: Word) -> Result<(), TopologyError> {
+        self.check(id)?;
+        self.machines[id].push_input(value);
+        self.ready[id] = true;
+        Ok(())
+    }
+
+    pub fn machines(&self) -> &[M] {
+        &self.machines
+    }
+
+    /// Every value sent to `Port::Sink`, in order.
+    pub fn sink(&self) -> &[Word] {
+        &self.sink
+    }
+
+    /// Sends `signal` to the first machine, runs, and returns the last value
+    /// that reached the sink.
+    pub fn run_signal(&mut self, signal: Word) -> Result<Option<Word>, TopologyError> {
+        self.push_input(0, signal)?;
+        self.run()?;
+        Ok(self.sink.last().copied())
+    }
+
+    /// Runs machines with pending input, lowest id first, until every
+    /// machine has halted. Fails with `Deadlock` if the remaining machines
+    /// are all waiting for input; pushing more and running again resumes.
+    pub fn run(&mut self) -> Result<(), TopologyError> {
+        while let Some(id) =
+            (0..self.machines.len()).find(|&id| self.ready[id] && !self.machines[id].is_halted())
+        {
+            self.ready[id] = false;
+            self.machines[id]
+                .run()
+                .map_err(|error| TopologyError::Machine { id, error })?;
+            if self.edges[id].is_empty() {
+                continue;
+            }
+            while let Some(value) = self.machines[id].next_output() {
+                for &port in &self.edges[id] {
+                    match port {
+                        Port::Machine(to) => {
+                            self.machines[to].push_input(value);
+                            self.ready[to] = true;
+                        }
+                        Port::Sink => self.sink.push(value),
+                    }
+                }
+            }
+        }
+
+        let blocked: Vec<_> = (0..self.machines.len())
+            .filter(|&id| !self.machines[id].is_halted())
+            .collect();
+        if blocked.is_empty() {
+            Ok(())
+        } else {
+            Err(TopologyError::Deadlock { blocked })
+        }
+    }
+
+    fn check(&self, id: usize) -> Result<(), TopologyError> {
+        if id < self.machines.len() {
+            Ok(())
+        } else {
+            Err(TopologyError::UnknownMachine(id))
+        }
+    }
+}
+
+impl<M: Machine> Default for Topology<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Port, Topology, TopologyError};
+    use crate::computer::{assembler::assemble, Computer};
+
+    const DOUBLER: &str = "3,11,102,2,11,11,4,11,1105,1,0,0";
+
+    #[test]
+    fn test_fan_out() {
+        let doubler = || Computer::new(DOUBLER.parse().unwrap());
+        let mut topology = Topology::new();
+        let source = topology.add(Computer::new("104,1,104,2,99".parse().unwrap()));
+        let left = topology.add(doubler());
+        let right = topology.add(doubler());
+        topology.connect(source, Port::Machine(left)).unwrap();
+        topology.connect(source, Port::Machine(right)).unwrap();
+        topology.connect(left, Port::Sink).unwrap();
+        topology.connect(right, Port::Machine(left)).unwrap();
+        assert_eq!(
+            topology.connect(3, Port::Sink),
+            Err(TopologyError::UnknownMachine(3))
+        );
+        assert_eq!(
+            topology.connect(left, Port::Machine(3)),
+            Err(TopologyError::UnknownMachine(3))
+        );
+
+        assert_eq!(
+            topology.run(),
+            Err(TopologyError::Deadlock {
+                blocked: vec![left, right]
+            })
+        );
+        assert_eq!(topology.sink(), &[2, 4, 4, 8]);
+        assert!(topology.machines()[right].get_output().is_empty());
+    }
+
+    #[test]
+    fn test_ring() {
+        let counter = assemble(
+            "
+            loop:   IN [n]
+                    ADD [n], #1, [n]
+                    OUT [n]
+                    LT [n], #5, [more]
+                    JT [more], loop
+                    HLT
+            n:      .data 0
+            more:   .data 0
+            ",
+        )
+        .unwrap();
+        let mut topology = Topology::ring([Computer::new(counter)]);
+        assert_eq!(topology.run_signal(0), Ok(Some(5)));
+        assert_eq!(topology.sink(), &[1, 2, 3, 4, 5]);
+
+        let mut topology = Topology::ring((0..2).map(|_| Computer::new(DOUBLER.parse().unwrap())));
+        assert_eq!(
+            topology.run(),
+            Err(TopologyError::Deadlock {
+                blocked: vec![0, 1]
+            })
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::VecDeque,
+    fmt, io,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use super::{error::ComputerError, instruction::Instruction, step::Step, Word};
+
+/// Receives every instruction a `Computer` executes.
+pub trait TraceSink {
+    fn record(&mut self, step: &Step);
+
+    /// Called instead of `record` when an instruction fails.
+    fn record_fault(&mut self, _fault: &Fault) {}
+}
+
+/// An instruction that stopped the machine with an error.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fault {
+    pub pointer: usize,
+    pub relative_base: Word,
+    /// `None` if the instruction could not be decoded.
+    pub instruction: Option<Instruction>,
+    pub error: ComputerError,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>6} rb={:<6} ", self.pointer, self.relative_base)?;
+        match &self.instruction {
+            Some(instruction) => write!(f, "{}", instruction)?,
+            None => f.write_str("?")?,
+        }
+        write!(f, " error: {}", self.error)
+    }
+}
+
+/// Writes one line per executed instruction.
+pub struct TextTrace<W: io::Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> TextTrace<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    /// The first write error, if any. Tracing stops once writing fails.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: io::Write> TraceSink for TextTrace<W> {
+    fn record(&mut self, step: &Step) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if let Err(err) = writeln!(self.writer, "{}", step) {
+            self.error = Some(err);
+        }
+    }
+
+    fn record_fault(&mut self, fault: &Fault) {
+        if self.error.is_some() {
+            return;
+        }
+
+        if let Err(err) = writeln!(self.writer, "{}", fault) {
+            self.error = Some(err);
+        }
+    }
+}
+
+/// Keeps the last `capacity` executed instructions for post-mortem dumps,
+/// and the instruction that failed, if any.
+#[derive(Clone, Debug)]
+pub struct RingTrace {
+    capacity: usize,
+    steps: VecDeque<Step>,
+    fault: Option<Fault>,
+}
+
+impl RingTrace {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            steps: VecDeque::with_capacity(capacity),
+            fault: None,
+        }
+    }
+
+    /// Oldest first.
+    pub fn steps(&self) -> impl Iterator<Item = &Step> {
+        self.steps.iter()
+    }
+
+    pub fn fault(&self) -> Option<&Fault> {
+        self.fault.as_ref()
+    }
+
+    /// The kept steps, followed by the fault.
+    pub fn dump(&self) -> String {
+        let steps = self.steps.iter().map(|step| format!("{}\n", step));
+        let fault = self.fault.iter().map(|fault| format!("{}\n", fault));
+        steps.chain(fault).collect()
+    }
+}
+
+impl TraceSink for RingTrace {
+    fn record(&mut self, step: &Step) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.steps.len() == self.capacity {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(step.clone());
+    }
+
+    fn record_fault(&mut self, fault: &Fault) {
+        self.fault = Some(fault.clone());
+    }
+}
+
+/// The sink attached to a computer. Clones of the computer share it.
+#[derive(Clone)]
+pub(crate) struct Trace(Arc<Mutex<dyn TraceSink + Send>>);
+
+impl Trace {
+    pub fn new<S: TraceSink + Send + 'static>(sink: Arc<Mutex<S>>) -> Self {
+        Self(sink)
+    }
+
+    pub fn record(&self, step: &Step) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .record(step);
+    }
+
+    pub fn record_fault(&self, fault: &Fault) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .record_fault(fault);
+    }
+}
+
+impl fmt::Debug for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Trace")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{RingTrace, TextTrace};
+    use crate::computer::Computer;
+
+    #[test]
+    fn test_text_trace() {
+        let sink = Arc::new(Mutex::new(TextTrace::new(Vec::new())));
+        let mut computer = Computer::new("109,3,204,-2,99".to_string().into());
+        computer.set_trace_sink(sink.clone());
+        computer.run().unwrap();
+
+        let log = String::from_utf8(sink.lock().unwrap().writer.clone()).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "     0 rb=0      ARB #3 operands=[3] next=2");
+        assert_eq!(
+            lines[1],
+            "     2 rb=3      OUT [rel -2] operands=[3] next=4"
+        );
+    }
+
+    #[test]
+    fn test_ring_trace() {
+        let ring = Arc::new(Mutex::new(RingTrace::new(2)));
+        let mut computer = Computer::new("1101,1,2,5,1101,0,0,0,99".to_string().into());
+        computer.set_trace_sink(ring.clone());
+        computer.run().unwrap();
+
+        let ring = ring.lock().unwrap();
+        let pointers: Vec<usize> = ring.steps().map(|step| step.pointer).collect();
+        assert_eq!(pointers, vec![4, 8]);
+        assert_eq!(ring.dump().lines().count(), 2);
+        drop(ring);
+
+        let ring = Arc::new(Mutex::new(RingTrace::new(2)));
+        let mut computer = Computer::new("1101,1,2,9,1,-1,0,0,99".to_string().into());
+        computer.set_trace_sink(ring.clone());
+        assert!(computer.run().is_err());
+
+        let ring = ring.lock().unwrap();
+        let dump = ring.dump();
+        assert_eq!(dump.lines().count(), 2);
+        assert_eq!(
+            dump.lines().last(),
+            Some("     4 rb=0      ADD [pos -1], [pos 0], [pos 0] error: negative address -1 used by instruction at address 4")
+        );
+    }
+}
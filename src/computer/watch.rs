@@ -0,0 +1,229 @@
+use std::{fmt, sync::Arc};
+
+use super::{step::Step, step::Write, Computer};
+
+/// A condition that pauses `Computer::run`.
+#[derive(Clone)]
+pub enum Watch {
+    /// The pointer reaches this address. `run` stops before the instruction
+    /// there executes, also when it starts there, except that running again
+    /// right after stopping at the breakpoint continues past it.
+    Pointer(usize),
+    /// An instruction reads a parameter from this address. An `Output` that
+    /// yields reports its value instead.
+    Read(usize),
+    /// An instruction writes to this address, even if the value is
+    /// unchanged.
+    Write(usize),
+    /// A write matches the predicate.
+    WriteIf(Arc<dyn Fn(&Write) -> bool + Send + Sync>),
+}
+
+impl Watch {
+    pub fn write_if<F: Fn(&Write) -> bool + Send + Sync + 'static>(predicate: F) -> Self {
+        Self::WriteIf(Arc::new(predicate))
+    }
+}
+
+impl fmt::Debug for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pointer(address) => f.debug_tuple("Pointer").field(address).finish(),
+            Self::Read(address) => f.debug_tuple("Read").field(address).finish(),
+            Self::Write(address) => f.debug_tuple("Write").field(address).finish(),
+            Self::WriteIf(_) => f.write_str("WriteIf"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WatchId(usize);
+
+/// Why `run` stopped with `RunState::Watch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hit {
+    /// The first watch added, if several matched.
+    pub id: WatchId,
+    pub reason: Reason,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// The pointer is at a breakpoint; the instruction there hasn't run.
+    Pointer(usize),
+    /// The instruction at `pointer` read `address`, and has run.
+    Read { pointer: usize, address: usize },
+    /// The instruction at `pointer` made this write, and has run.
+    Write { pointer: usize, write: Write },
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Watches {
+    watches: Vec<(WatchId, Watch)>,
+    next: usize,
+    /// The breakpoint address `run` last stopped at, until an instruction
+    /// runs.
+    paused_at: Option<usize>,
+}
+
+impl Watches {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    /// A breakpoint at `pointer`, unless `run` already stopped there.
+    pub(crate) fn breakpoint(&mut self, pointer: usize) -> Option<Hit> {
+        if self.paused_at == Some(pointer) {
+            return None;
+        }
+        let hit = self.at_pointer(pointer)?;
+        self.paused_at = Some(pointer);
+        Some(hit)
+    }
+
+    /// Called after each executed instruction.
+    pub(crate) fn resume(&mut self) {
+        self.paused_at = None;
+    }
+
+    pub(crate) fn at_pointer(&self, pointer: usize) -> Option<Hit> {
+        self.watches.iter().find_map(|(id, watch)| match watch {
+            Watch::Pointer(address) if *address == pointer => Some(Hit {
+                id: *id,
+                reason: Reason::Pointer(pointer),
+            }),
+            _ => None,
+        })
+    }
+
+    pub(crate) fn after(&self, step: &Step) -> Option<Hit> {
+        let pointer = step.pointer;
+        let reads = &step.reads;
+        self.watches.iter().find_map(|(id, watch)| {
+            let reason = match (watch, step.write) {
+                (Watch::Read(address), _) if reads.contains(address) => Reason::Read {
+                    pointer,
+                    address: *address,
+                },
+                (Watch::Write(address), Some(write)) if write.address == *address => {
+                    Reason::Write { pointer, write }
+                }
+                (Watch::WriteIf(predicate), Some(write)) if predicate(&write) => {
+                    Reason::Write { pointer, write }
+                }
+                _ => return None,
+            };
+            Some(Hit { id: *id, reason })
+        })
+    }
+}
+
+impl Computer {
+    pub fn add_watch(&mut self, watch: Watch) -> WatchId {
+        let id = WatchId(self.watches.next);
+        self.watches.next += 1;
+        self.watches.watches.push((id, watch));
+        id
+    }
+
+    /// Returns whether the watch existed.
+    pub fn remove_watch(&mut self, id: WatchId) -> bool {
+        let len = self.watches.watches.len();
+        self.watches.watches.retain(|(other, _)| *other != id);
+        self.watches.watches.len() != len
+    }
+
+    pub fn clear_watches(&mut self) {
+        self.watches.watches.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Reason, Watch};
+    use crate::computer::{step::Write, Computer, RunState};
+
+    #[test]
+    fn test_watches() {
+        // counts [17] down from 3, printing it each time
+        let program = "1001,17,-1,17,4,17,1005,17,0,99,0,0,0,0,0,0,0,3";
+        let mut computer = Computer::new(program.parse().unwrap());
+        let breakpoint = computer.add_watch(Watch::Pointer(4));
+        let read = computer.add_watch(Watch::Read(17));
+
+        let state = computer.run().unwrap();
+        let RunState::Watch(hit) = state else {
+            panic!("unexpected {:?}", state);
+        };
+        assert_eq!(hit.id, read);
+        assert_eq!(
+            hit.reason,
+            Reason::Read {
+                pointer: 0,
+                address: 17
+            }
+        );
+        assert_eq!(computer.pointer(), 4);
+
+        // the read stopped on the breakpoint, which still fires
+        let state = computer.run().unwrap();
+        assert!(matches!(state, RunState::Watch(hit) if hit.id == breakpoint));
+        assert_eq!(computer.pointer(), 4);
+        assert!(computer.get_output().is_empty());
+
+        let state = computer.run().unwrap();
+        assert!(matches!(state, RunState::Watch(hit) if hit.id == read));
+        assert_eq!(computer.pointer(), 6);
+        assert!(computer.remove_watch(read));
+        assert!(!computer.remove_watch(read));
+
+        let state = computer.run().unwrap();
+        assert!(matches!(state, RunState::Watch(hit) if hit.id == breakpoint));
+        assert_eq!(computer.get_output(), vec![2]);
+        computer.clear_watches();
+
+        let zero = computer.add_watch(Watch::write_if(|write| write.new == 0));
+        let state = computer.run().unwrap();
+        let RunState::Watch(hit) = state else {
+            panic!("unexpected {:?}", state);
+        };
+        assert_eq!(hit.id, zero);
+        let write = Write {
+            address: 17,
+            old: 1,
+            new: 0,
+        };
+        assert_eq!(hit.reason, Reason::Write { pointer: 0, write });
+        assert_eq!(computer.run(), Ok(RunState::Halted));
+        assert_eq!(computer.get_output(), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_breakpoint_after_other_watch() {
+        let program = "1001,17,-1,17,4,17,1005,17,0,99,0,0,0,0,0,0,0,3";
+        let mut computer = Computer::new(program.parse().unwrap());
+        let breakpoint = computer.add_watch(Watch::Pointer(4));
+        let write = computer.add_watch(Watch::Write(17));
+
+        let state = computer.run().unwrap();
+        assert!(matches!(state, RunState::Watch(hit) if hit.id == write));
+        assert_eq!(computer.pointer(), 4);
+        let state = computer.run().unwrap();
+        assert!(matches!(state, RunState::Watch(hit) if hit.id == breakpoint));
+        assert!(computer.get_output().is_empty());
+
+        // continues past the breakpoint it stopped at, even if watches are
+        // replaced in between
+        computer.clear_watches();
+        let breakpoint = computer.add_watch(Watch::Pointer(4));
+        let state = computer.run().unwrap();
+        assert!(matches!(state, RunState::Watch(hit) if hit.id == breakpoint));
+        assert_eq!(computer.get_output(), vec![2]);
+
+        // a breakpoint on the address a fresh run starts at fires first
+        let mut computer = Computer::new(program.parse().unwrap());
+        computer.add_watch(Watch::Pointer(0));
+        assert!(matches!(computer.run(), Ok(RunState::Watch(_))));
+        assert_eq!(computer.pointer(), 0);
+    }
+}
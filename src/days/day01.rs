@@ -16,6 +16,7 @@ pub(crate) fn run(input: String) {
     println!("Part 2: {}", part2);
 }
 
+#[allow(clippy::implicit_saturating_sub)]
 fn fuel_for_module(mass: u32) -> u32 {
     let fuel = mass / 3;
     if fuel >= 2 {
@@ -1,39 +1,43 @@
-use crate::computer::Computer;
+use aoc::computer::{
+    compiled::CompiledComputer,
+    machine::Machine,
+    symbolic::{solve, Symbol},
+};
 
 pub(crate) fn run(input: String) {
-    let mut computer = Computer::new(input.clone().into());
-    computer.memory[1] = 12;
-    computer.memory[2] = 2;
-    computer.run();
-    let val = computer.memory.first().unwrap();
+    let template = CompiledComputer::new(input.into());
+
+    let mut computer = template.clone();
+    computer.set_memory(1, 12);
+    computer.set_memory(2, 2);
+    computer.run().unwrap();
+    let val = computer.memory().first().unwrap();
     println!("Part 1: {}", *val);
 
-    for noun in 0..=99 {
-        for verb in 0..=99 {
-            let mut computer = Computer::new(input.clone().into());
-            computer.memory[1] = noun;
-            computer.memory[2] = verb;
-            computer.run();
-            let val = computer.memory.first().unwrap();
-            if *val == 19690720 {
-                let result = 100 * noun + verb;
-                println!("Part 2: {}", result);
-                return;
-            }
-        }
+    let symbols: Vec<_> = (1..=2)
+        .map(|address| Symbol {
+            address,
+            domain: 0..=99,
+        })
+        .collect();
+    match solve(template.memory(), &symbols, 19690720) {
+        Some(values) => println!("Part 2: {}", 100 * values[0] + values[1]),
+        None => println!("Part 2: no noun and verb give 19690720"),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::computer::Computer;
+    use aoc::computer::machine::Backend;
 
     #[test]
     fn test_computer() {
         let input = "1,9,10,3,2,3,11,0,99,30,40,50".to_string();
-        let mut computer = Computer::new(input.into());
-        computer.run();
-        let val = computer.memory.get(0);
-        assert_eq!(val, Some(&3500));
+        for backend in Backend::ALL {
+            let mut computer = backend.machine(input.clone().into());
+            computer.run().unwrap();
+            let val = computer.memory().get(0);
+            assert_eq!(val, Some(&3500));
+        }
     }
 }
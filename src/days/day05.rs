@@ -1,17 +1,51 @@
-use crate::computer::Computer;
+use aoc::computer::Computer;
 
 pub(crate) fn run(input: String) {
     let mut computer = Computer::new(input.clone().into());
     computer.set_input(vec![1]);
-    computer.run();
+    computer.run().unwrap();
     let output = computer.get_output();
     let diag_code = output.last();
     println!("Part 1: {}", diag_code.unwrap());
 
     let mut computer = Computer::new(input.clone().into());
     computer.set_input(vec![5]);
-    computer.run();
+    computer.run().unwrap();
     let output = computer.get_output();
     let diag_code = output.last();
     println!("Part 1: {}", diag_code.unwrap());
 }
+
+#[cfg(test)]
+mod tests {
+    use aoc::computer::machine::Backend;
+
+    #[test]
+    fn test_equal_to_eight() {
+        let program = "3,9,8,9,10,9,4,9,99,-1,8".to_string();
+        for backend in Backend::ALL {
+            for (input, expected) in [(8, 1), (7, 0)] {
+                let mut computer = backend.machine(program.clone().into());
+                computer.set_input(vec![input]);
+                computer.run().unwrap();
+                assert_eq!(computer.get_output(), vec![expected]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compare_with_jumps() {
+        let program = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,\
+                       1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,\
+                       1105,1,46,98,99"
+            .to_string();
+        for backend in Backend::ALL {
+            for (input, expected) in [(7, 999), (8, 1000), (9, 1001)] {
+                let mut computer = backend.machine(program.clone().into());
+                computer.set_input(vec![input]);
+                computer.run().unwrap();
+                assert_eq!(computer.get_output(), vec![expected]);
+            }
+        }
+    }
+}
@@ -1,12 +1,16 @@
-use crate::computer::Computer;
+use aoc::computer::{
+    machine::{Backend, Machine},
+    topology::Topology,
+    Word,
+};
 use itertools::Itertools;
 
 pub(crate) fn run(input: String) {
-    let amps = AmpSet::new(0, 4, input.clone());
+    let amps = AmpSet::new(0, 4, input.clone()).with_backend(Backend::Compiled);
     let max = amps.find_max_thruster_signal();
     println!("Part 1: {}", max);
 
-    let amps = AmpSet::new(5, 9, input.clone());
+    let amps = AmpSet::new(5, 9, input.clone()).with_backend(Backend::Compiled);
     let max = amps.find_max_feedback_signal();
     println!("Part 2: {}", max);
 }
@@ -15,6 +19,7 @@ struct AmpSet {
     phase_low: usize,
     phase_high: usize,
     program: String,
+    backend: Backend,
 }
 
 impl AmpSet {
@@ -23,9 +28,22 @@ impl AmpSet {
             phase_low,
             phase_high,
             program,
+            backend: Backend::Interpreter,
         }
     }
 
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// A machine that reads `phase` first.
+    fn amp(&self, phase: usize) -> Box<dyn Machine + Send> {
+        let mut amp = self.backend.machine(self.program.clone().into());
+        amp.push_input(Word::try_from(phase).unwrap());
+        amp
+    }
+
     pub fn find_max_thruster_signal(&self) -> usize {
         let range = self.phase_low..=self.phase_high;
         let count = range.try_len().unwrap();
@@ -49,68 +67,35 @@ impl AmpSet {
     }
 
     fn get_thruster_signal_for_sequence(&self, sequence: Vec<usize>) -> usize {
-        let mut last_output = 0;
-        for i in sequence {
-            let mut computer = Computer::new(self.program.clone().into());
-            computer.set_input(vec![i.try_into().unwrap(), last_output]);
-            computer.run();
-            let output = computer.get_output()[0];
-            last_output = output;
-        }
-
-        last_output as usize
+        self.run_amplifiers(&sequence, false)
     }
 
     fn get_feedback_signal_for_sequence(&self, sequence: Vec<usize>) -> usize {
-        let mut computers = vec![
-            Computer::new(self.program.clone().into()),
-            Computer::new(self.program.clone().into()),
-            Computer::new(self.program.clone().into()),
-            Computer::new(self.program.clone().into()),
-            Computer::new(self.program.clone().into()),
-        ];
-
-        for (index, phase) in sequence.iter().enumerate() {
-            let computer = computers.get_mut(index).unwrap();
-            computer.set_yield_on_output(true);
-            computer.push_input((*phase).try_into().unwrap());
-        }
-
-        let mut next_input = Some(0);
-        let mut last_output = None;
-        loop {
-            for (index, computer) in computers.iter_mut().enumerate() {
-                if let Some(next) = next_input {
-                    computer.push_input(next);
-                }
-                next_input = None;
-                computer.run();
+        self.run_amplifiers(&sequence, true)
+    }
 
-                if computer.is_halted() && index == 4 {
-                    if let Some(output) = computers.last().unwrap().get_output().first() {
-                        return *output as usize;
-                    } else {
-                        return last_output.unwrap_or(0);
-                    }
-                } else if let Some(output) = computer.next_output() {
-                    next_input = Some(output);
-                    last_output = Some(output as usize);
-                }
-            }
-        }
+    fn run_amplifiers(&self, sequence: &[usize], feedback: bool) -> usize {
+        let amps = sequence.iter().map(|&phase| self.amp(phase));
+        Topology::pipeline(amps, feedback)
+            .run_signal(0)
+            .unwrap()
+            .unwrap() as usize
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::days::day07::AmpSet;
+    use aoc::computer::machine::Backend;
 
     #[test]
     fn test_find_max_thruster_signal() {
         let program = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0".to_string();
-        let amp_set = AmpSet::new(0, 4, program);
-        let max = amp_set.find_max_thruster_signal();
-        assert_eq!(max, 43210);
+        for backend in Backend::ALL {
+            let amp_set = AmpSet::new(0, 4, program.clone()).with_backend(backend);
+            let max = amp_set.find_max_thruster_signal();
+            assert_eq!(max, 43210);
+        }
     }
 
     #[test]
@@ -118,8 +103,10 @@ mod tests {
         let program =
             "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5"
                 .to_string();
-        let amp_set = AmpSet::new(5, 9, program);
-        let max = amp_set.find_max_feedback_signal();
-        assert_eq!(max, 139629729);
+        for backend in Backend::ALL {
+            let amp_set = AmpSet::new(5, 9, program.clone()).with_backend(backend);
+            let max = amp_set.find_max_feedback_signal();
+            assert_eq!(max, 139629729);
+        }
     }
 }
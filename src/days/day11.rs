@@ -1,28 +1,23 @@
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
 
-use crate::{
-    computer::Computer,
-    grid::{Cell, Direction, Grid, Turn},
+use crate::grid::{Cell, Direction, Grid, Turn};
+use aoc::computer::{
+    device::{InputFn, OutputFn},
+    Computer, RunState, Word,
 };
 
 pub(crate) fn run(input: String) {
-    let mut computer = Computer::new(input.clone().into());
-    computer.set_yield_on_output(false);
-    let mut painted: HashSet<Cell> = HashSet::new();
-    let mut visited: HashSet<Cell> = HashSet::new();
-    let mut robot = Robot::new();
-    start_robot(&mut robot, &mut computer, &mut painted, &mut visited);
-    let count = visited.len();
+    let hull = paint(&input, Hull::new());
+    let count = hull.visited.len();
     println!("Part 1: {}", count);
 
     // Start on a white panel for part 2
-    let mut computer = Computer::new(input.clone().into());
-    computer.set_yield_on_output(false);
-    let mut painted: HashSet<Cell> = HashSet::new();
-    painted.insert((0, 0).into());
-    let mut visited: HashSet<Cell> = HashSet::new();
-    let mut robot = Robot::new();
-    start_robot(&mut robot, &mut computer, &mut painted, &mut visited);
+    let mut hull = Hull::new();
+    hull.painted.insert((0, 0).into());
+    let painted = paint(&input, hull).painted;
 
     let mut grid: Grid<bool> = Grid::new();
     for cell in painted.iter() {
@@ -44,32 +39,61 @@ pub(crate) fn run(input: String) {
     println!("\n");
 }
 
-fn start_robot(
-    robot: &mut Robot,
-    computer: &mut Computer,
-    painted: &mut HashSet<Cell>,
-    visited: &mut HashSet<Cell>,
-) {
-    while !computer.is_halted() {
-        let current_color = painted.get(&robot.position).map(|_| 1).unwrap_or(0);
-        computer.push_input(current_color);
-        computer.run();
-
-        let paint_num = computer.next_output().unwrap();
+/// Runs the robot program with the camera and the motors wired to `hull`.
+fn paint(program: &str, hull: Hull) -> Hull {
+    let hull = Arc::new(Mutex::new(hull));
+    let mut computer = Computer::new(program.parse().unwrap());
+    let camera = Arc::clone(&hull);
+    computer.set_input_device(InputFn(move || Some(camera.lock().unwrap().color())));
+    let motors = Arc::clone(&hull);
+    computer.set_output_device(OutputFn(move |value| motors.lock().unwrap().receive(value)));
+    assert_eq!(computer.run().unwrap(), RunState::Halted);
+
+    drop(computer);
+    Arc::into_inner(hull).unwrap().into_inner().unwrap()
+}
+
+struct Hull {
+    robot: Robot,
+    painted: HashSet<Cell>,
+    visited: HashSet<Cell>,
+    /// The color to paint, while waiting for the direction to turn.
+    paint: Option<Word>,
+}
+
+impl Hull {
+    pub fn new() -> Self {
+        Self {
+            robot: Robot::new(),
+            painted: HashSet::new(),
+            visited: HashSet::new(),
+            paint: None,
+        }
+    }
+
+    pub fn color(&self) -> Word {
+        self.painted
+            .get(&self.robot.position)
+            .map(|_| 1)
+            .unwrap_or(0)
+    }
+
+    /// The program outputs a color to paint, then a direction to turn.
+    pub fn receive(&mut self, value: Word) {
+        let Some(paint_num) = self.paint.take() else {
+            self.paint = Some(value);
+            return;
+        };
+
         if paint_num == 1 {
-            painted.insert(robot.position);
-            visited.insert(robot.position);
+            self.painted.insert(self.robot.position);
+            self.visited.insert(self.robot.position);
         } else {
-            painted.remove(&robot.position);
+            self.painted.remove(&self.robot.position);
         }
-        let turn_num = computer.next_output().unwrap();
-        let turn = if turn_num == 1 {
-            Turn::Right
-        } else {
-            Turn::Left
-        };
-        robot.turn(turn);
-        robot.forward();
+        let turn = if value == 1 { Turn::Right } else { Turn::Left };
+        self.robot.turn(turn);
+        self.robot.forward();
     }
 }
 
@@ -98,8 +122,36 @@ impl Robot {
 
 #[cfg(test)]
 mod test {
+    use super::{paint, Hull, Robot};
+    use crate::grid::{Cell, Turn};
+
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_thing() {
         assert!(true);
     }
+
+    #[test]
+    fn test_robot() {
+        let mut robot = Robot::new();
+        robot.forward();
+        robot.turn(Turn::Right);
+        robot.forward();
+        robot.forward();
+        robot.turn(Turn::Right);
+        robot.turn(Turn::Right);
+        robot.turn(Turn::Left);
+        robot.forward();
+        assert_eq!(robot.position, Cell(2, 0));
+    }
+
+    #[test]
+    fn test_paint() {
+        // paints white and turns left, then paints black and turns right
+        let program = "3,100,104,1,104,0,3,100,104,0,104,1,99";
+        let hull = paint(program, Hull::new());
+        assert_eq!(hull.visited.len(), 1);
+        assert!(hull.painted.contains(&Cell(0, 0)));
+        assert_eq!(hull.robot.position, Cell(-1, 1));
+    }
 }
@@ -50,7 +50,7 @@ impl<V: Clone + Default> Grid<V> {
         self.data.get(cell)
     }
 
-    pub fn rows_iter(&self) -> RowsIter<V> {
+    pub fn rows_iter(&self) -> RowsIter<'_, V> {
         RowsIter {
             grid: self,
             current_y: self.extent.north,
@@ -96,6 +96,7 @@ pub(crate) struct RowIter<'a, V: Clone + Default> {
 }
 
 impl<'a, V: Clone + Default> RowIter<'a, V> {
+    #[allow(dead_code)]
     pub fn enumerate(self) -> RowIterWithCell<'a, V> {
         RowIterWithCell { inner: self }
     }
@@ -1,21 +1,105 @@
-use clap::Parser;
-use std::{error::Error, fs, path::Path};
+use aoc::computer::Word;
+use clap::{Parser, Subcommand};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
 
-pub mod computer;
+mod commands;
 mod days;
 pub mod grid;
 pub mod util;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
     #[arg(short, long)]
-    day: String,
+    day: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Assemble Intcode source into a comma-separated program
+    Asm { source: PathBuf },
+    /// Print the control-flow graph of an Intcode program as Graphviz DOT
+    Cfg { program: PathBuf },
+    /// Print a mnemonic listing of an Intcode program
+    Disasm { program: PathBuf },
+    /// Compare the interpreter against a reference evaluator on random programs
+    #[cfg(feature = "fuzz")]
+    Fuzz {
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+        /// Number of programs to generate
+        #[arg(long, default_value_t = 10000)]
+        runs: usize,
+        /// Instructions each program may execute
+        #[arg(long, default_value_t = 1000)]
+        steps: usize,
+        /// Where to write the minimized failing program, with its input on
+        /// the second line
+        #[arg(long, default_value = "fuzz-failure.txt")]
+        out: PathBuf,
+    },
+    /// Run an Intcode program with stdin as input and stdout as output
+    Run {
+        program: PathBuf,
+        /// Send input lines and print output as ASCII text
+        #[arg(long)]
+        ascii: bool,
+        /// Set a memory cell before running, e.g. `--set 1=12`
+        #[arg(long = "set", value_name = "ADDRESS=VALUE", value_parser = commands::run::parse_patch)]
+        patches: Vec<(usize, Word)>,
+        /// Print each output value as soon as it is produced, rather than
+        /// when the program waits for input or halts
+        #[arg(long = "yield")]
+        yield_on_output: bool,
+    },
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
-    let day = util::normalize_day(&args.day);
+    match (args.command, args.day) {
+        (Some(Command::Asm { source }), _) => commands::asm::run(&source),
+        (Some(Command::Cfg { program }), _) => commands::cfg::run(&program),
+        (Some(Command::Disasm { program }), _) => commands::disasm::run(&program),
+        #[cfg(feature = "fuzz")]
+        (
+            Some(Command::Fuzz {
+                seed,
+                runs,
+                steps,
+                out,
+            }),
+            _,
+        ) => commands::fuzz::run(seed, runs, steps, &out),
+        (
+            Some(Command::Run {
+                program,
+                ascii,
+                patches,
+                yield_on_output,
+            }),
+            _,
+        ) => {
+            let options = commands::run::Options {
+                ascii,
+                patches,
+                yield_on_output,
+            };
+            commands::run::run(&program, &options)
+        }
+        (None, Some(day)) => run_day(&day),
+        (None, None) => Err("either --day or a subcommand is required".into()),
+    }
+}
+
+fn run_day(day: &str) -> Result<(), Box<dyn Error>> {
+    let day = util::normalize_day(day);
     let input_path = format!("inputs/{}.txt", day);
     let input = fs::read_to_string(Path::new(&input_path))?;
 